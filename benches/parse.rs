@@ -0,0 +1,74 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mqtt_packet::{frame, DataType, FixedHeader, PacketType, Property};
+use std::io;
+
+fn variable_byte_int(c: &mut Criterion) {
+  let cases: [(&str, &[u8]); 4] = [
+    ("one byte", &[0x7F]),
+    ("two bytes", &[0xFF, 0x7F]),
+    ("three bytes", &[0xFF, 0xFF, 0x7F]),
+    ("four bytes", &[0xFF, 0xFF, 0xFF, 0x7F]),
+  ];
+
+  let mut group = c.benchmark_group("variable byte int");
+
+  for (name, bytes) in cases.iter() {
+    group.bench_function(*name, |b| {
+      b.iter(|| {
+        let mut reader = io::BufReader::new(black_box(*bytes));
+        DataType::parse_variable_byte_int(&mut reader).unwrap()
+      })
+    });
+  }
+
+  group.finish();
+}
+
+fn publish_properties() -> Vec<u8> {
+  Property::builder()
+    .set_payload_format_indicator(true)
+    .set_message_expiry_interval(3600)
+    .set_topic_alias(1)
+    .set_content_type(String::from("application/json"))
+    .add_user_property(String::from("key"), String::from("value"))
+    .build()
+    .generate()
+    .unwrap()
+}
+
+fn property_block(c: &mut Criterion) {
+  let bytes = publish_properties();
+
+  c.bench_function("property block", |b| {
+    b.iter(|| {
+      let mut reader = io::BufReader::new(black_box(&bytes[..]));
+      Property::new(&mut reader).unwrap()
+    })
+  });
+
+  c.bench_function("property block from slice", |b| {
+    b.iter(|| Property::from_slice(black_box(&bytes[..])).unwrap())
+  });
+}
+
+/// There is no PUBLISH parser yet, so this parses the parts that exist: the
+/// fixed header and the property block of 10k back to back packets.
+fn publish_packets(c: &mut Criterion) {
+  let properties = publish_properties();
+  let packet = frame(PacketType::PUBLISH, 0x02, properties).unwrap();
+
+  let stream = packet.repeat(10_000);
+
+  c.bench_function("10k publish headers", |b| {
+    b.iter(|| {
+      let mut reader = io::BufReader::new(black_box(&stream[..]));
+      for _ in 0..10_000 {
+        FixedHeader::new(&mut reader).unwrap();
+        Property::new(&mut reader).unwrap();
+      }
+    })
+  });
+}
+
+criterion_group!(benches, variable_byte_int, property_block, publish_packets);
+criterion_main!(benches);
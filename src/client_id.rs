@@ -0,0 +1,104 @@
+use crate::Error;
+
+/// Validate a CONNECT Client Identifier.
+///
+/// [3.1.3.1 Client Identifier](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901059)
+///
+/// The Server MUST allow ClientID’s which are between 1 and 23 UTF-8 encoded
+/// bytes in length, and that contain only the characters
+/// "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"
+/// [MQTT-3.1.3-5]. The Server MAY allow ClientID’s that contain more than 23
+/// encoded bytes, and other characters.
+///
+/// With `strict` set, only identifiers in the guaranteed set are accepted and
+/// anything else returns `Error::ProtocolError`. Otherwise any UTF-8 Encoded
+/// String is accepted, and `Error::MalformedPacket` is returned for
+/// identifiers over 65,535 bytes or containing U+0000 [MQTT-1.5.4-2].
+///
+/// An empty identifier passes when `strict` is false; see
+/// `validate_empty_client_id` for the Clean Start check.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::{validate_client_id, Error};
+///
+/// assert!(validate_client_id("sensor42", true).is_ok());
+/// assert_eq!(validate_client_id("sensor-42", true).unwrap_err(), Error::ProtocolError);
+/// assert!(validate_client_id("sensor-42", false).is_ok());
+/// ```
+pub fn validate_client_id(id: &str, strict: bool) -> Result<(), Error> {
+  if strict {
+    if id.is_empty() || id.len() > 23 || !id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+      return Err(Error::ProtocolError);
+    }
+  } else if id.len() > 65535 || id.contains('\u{0}') {
+    return Err(Error::MalformedPacket);
+  }
+
+  Ok(())
+}
+
+/// Check that an empty Client Identifier is only used together with Clean
+/// Start. The Server assigns an identifier in that case, and without Clean
+/// Start there would be no way to resume the session later. Returns
+/// `Error::ProtocolError` for an empty identifier without Clean Start.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::{validate_empty_client_id, Error};
+///
+/// assert!(validate_empty_client_id("", true).is_ok());
+/// assert_eq!(validate_empty_client_id("", false).unwrap_err(), Error::ProtocolError);
+/// ```
+pub fn validate_empty_client_id(id: &str, clean_start: bool) -> Result<(), Error> {
+  if id.is_empty() && !clean_start {
+    return Err(Error::ProtocolError);
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{validate_client_id, validate_empty_client_id};
+  use crate::Error;
+
+  #[test]
+  fn strict() {
+    assert!(validate_client_id("a", true).is_ok());
+    assert!(validate_client_id("0123456789abcdefghijklm", true).is_ok());
+
+    let errors = ["", "0123456789abcdefghijklmn", "client id", "clíent"];
+    for id in errors.iter() {
+      assert_eq!(
+        validate_client_id(id, true).unwrap_err(),
+        Error::ProtocolError
+      );
+    }
+  }
+
+  #[test]
+  fn general() {
+    assert!(validate_client_id("", false).is_ok());
+    assert!(validate_client_id("clíent/with spaces", false).is_ok());
+    assert!(validate_client_id(&"a".repeat(65535), false).is_ok());
+
+    let err = validate_client_id(&"a".repeat(65536), false).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+
+    let err = validate_client_id("client\u{0}", false).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+  }
+
+  #[test]
+  fn empty() {
+    assert!(validate_empty_client_id("", true).is_ok());
+    assert!(validate_empty_client_id("client", false).is_ok());
+    assert_eq!(
+      validate_empty_client_id("", false).unwrap_err(),
+      Error::ProtocolError
+    );
+  }
+}
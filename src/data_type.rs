@@ -1,10 +1,11 @@
 use crate::Error;
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::string::String;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum VariableByte {
   One(u8),
   Two(u16),
@@ -13,7 +14,10 @@ pub enum VariableByte {
 }
 
 /// Data types defined by the MQTT v5 spec.
-#[derive(Debug, PartialEq)]
+///
+/// Values are ordered by variant, in the order declared below, then by the
+/// contained value. Strings and binary data compare lexicographically.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum DataType {
   Byte(u8),
   TwoByteInteger(u16),
@@ -24,6 +28,125 @@ pub enum DataType {
   Utf8StringPair(String, String),
 }
 
+/// The variant of a DataType, without its value. Used to describe the data
+/// type a property identifier expects.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DataTypeKind {
+  Byte,
+  TwoByteInteger,
+  FourByteInteger,
+  VariableByteInteger,
+  Utf8EncodedString,
+  BinaryData,
+  Utf8StringPair,
+}
+
+impl VariableByte {
+  /// Create a VariableByte using the variant that matches the number of bytes
+  /// needed to encode the value. Returns `Error::GenerateError` if the value
+  /// is larger than 268,435,455, the maximum a Variable Byte Integer can hold.
+  ///
+  /// Prefer this over building a variant directly: `VariableByte::One(200)`
+  /// is out of range for `One` and would not encode correctly.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, VariableByte};
+  ///
+  /// assert_eq!(VariableByte::new(200).unwrap(), VariableByte::Two(200));
+  /// assert_eq!(VariableByte::new(268_435_456).unwrap_err(), Error::GenerateError);
+  /// ```
+  pub fn new(value: u32) -> Result<Self, Error> {
+    if value > 268_435_455 {
+      return Err(Error::GenerateError);
+    }
+    Ok(Self::from(value))
+  }
+}
+
+impl From<u32> for VariableByte {
+  /// Picks the variant that matches the number of bytes needed to encode the value.
+  fn from(value: u32) -> Self {
+    match value {
+      n if n <= 127 => Self::One(n as u8),
+      n if n <= 16383 => Self::Two(n as u16),
+      n if n <= 2_097_151 => Self::Three(n),
+      n => Self::Four(n),
+    }
+  }
+}
+
+impl From<&VariableByte> for u32 {
+  fn from(t: &VariableByte) -> Self {
+    match t {
+      VariableByte::One(value) => u32::from(*value),
+      VariableByte::Two(value) => u32::from(*value),
+      VariableByte::Three(value) | VariableByte::Four(value) => *value,
+    }
+  }
+}
+
+impl fmt::Display for DataType {
+  /// Integers are written in decimal, strings are quoted and escaped, and
+  /// binary data is written as hex, e.g. `0x0a0b`.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DataType::Byte(value) => write!(f, "{}", value),
+      DataType::TwoByteInteger(value) => write!(f, "{}", value),
+      DataType::FourByteInteger(value) => write!(f, "{}", value),
+      DataType::VariableByteInteger(value) => write!(f, "{}", u32::from(value)),
+      DataType::Utf8EncodedString(value) => write!(f, "{:?}", value),
+      DataType::BinaryData(value) => {
+        f.write_str("0x")?;
+        for byte in value {
+          write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+      }
+      DataType::Utf8StringPair(name, value) => write!(f, "{:?}: {:?}", name, value),
+    }
+  }
+}
+
+impl From<u8> for DataType {
+  fn from(value: u8) -> Self {
+    DataType::Byte(value)
+  }
+}
+
+impl From<u16> for DataType {
+  fn from(value: u16) -> Self {
+    DataType::TwoByteInteger(value)
+  }
+}
+
+/// Converts to `DataType::FourByteInteger`. Use `VariableByte::from` for
+/// Variable Byte Integers.
+impl From<u32> for DataType {
+  fn from(value: u32) -> Self {
+    DataType::FourByteInteger(value)
+  }
+}
+
+impl From<&str> for DataType {
+  fn from(value: &str) -> Self {
+    DataType::Utf8EncodedString(value.to_string())
+  }
+}
+
+impl From<String> for DataType {
+  fn from(value: String) -> Self {
+    DataType::Utf8EncodedString(value)
+  }
+}
+
+impl From<Vec<u8>> for DataType {
+  fn from(value: Vec<u8>) -> Self {
+    DataType::BinaryData(value)
+  }
+}
+
 impl From<DataType> for u16 {
   fn from(t: DataType) -> Self {
     if let DataType::TwoByteInteger(value) = t {
@@ -35,6 +158,66 @@ impl From<DataType> for u16 {
 }
 
 impl DataType {
+  /// The kind of this value.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{DataType, DataTypeKind};
+  ///
+  /// assert_eq!(DataType::Byte(1).kind(), DataTypeKind::Byte);
+  /// ```
+  pub fn kind(&self) -> DataTypeKind {
+    match self {
+      DataType::Byte(_) => DataTypeKind::Byte,
+      DataType::TwoByteInteger(_) => DataTypeKind::TwoByteInteger,
+      DataType::FourByteInteger(_) => DataTypeKind::FourByteInteger,
+      DataType::VariableByteInteger(_) => DataTypeKind::VariableByteInteger,
+      DataType::Utf8EncodedString(_) => DataTypeKind::Utf8EncodedString,
+      DataType::BinaryData(_) => DataTypeKind::BinaryData,
+      DataType::Utf8StringPair(_, _) => DataTypeKind::Utf8StringPair,
+    }
+  }
+
+  /// Create a DataType::Utf8EncodedString, returning `Error::GenerateError` if
+  /// the string is longer than the 65,535 bytes its length prefix can hold.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{DataType, Error};
+  ///
+  /// assert_eq!(
+  ///   DataType::utf8("hello").unwrap(),
+  ///   DataType::Utf8EncodedString(String::from("hello"))
+  /// );
+  /// assert_eq!(DataType::utf8("a".repeat(65536)).unwrap_err(), Error::GenerateError);
+  /// ```
+  pub fn utf8(value: impl Into<String>) -> Result<Self, Error> {
+    let value = value.into();
+    if value.len() > 65535 {
+      return Err(Error::GenerateError);
+    }
+    Ok(Self::Utf8EncodedString(value))
+  }
+
+  /// Create a DataType::VariableByteInteger using `VariableByte::new`, which
+  /// picks the smallest variant that can hold the value and returns
+  /// `Error::GenerateError` if it is out of range.
+  pub fn variable_byte(value: u32) -> Result<Self, Error> {
+    Ok(Self::VariableByteInteger(VariableByte::new(value)?))
+  }
+
+  /// Create a DataType::BinaryData, returning `Error::GenerateError` if the
+  /// data is longer than the 65,535 bytes its length prefix can hold.
+  pub fn binary(value: impl Into<Vec<u8>>) -> Result<Self, Error> {
+    let value = value.into();
+    if value.len() > 65535 {
+      return Err(Error::GenerateError);
+    }
+    Ok(Self::BinaryData(value))
+  }
+
   /// Reads one byte from the reader and attempts to convert the byte to DataType::Byte (u8).
   ///
   /// [1.5.1 Bits](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901007)
@@ -114,20 +297,35 @@ impl DataType {
   }
 
   fn parse_string<R: io::Read>(reader: &mut R) -> Result<String, Error> {
-    // get the expected length of the string
-    let mut length_buffer = [0; 2];
+    let buffer = Self::read_length_prefixed(reader)?;
+    let s = String::from_utf8(buffer)?;
 
-    reader.read_exact(&mut length_buffer)?;
+    Ok(s)
+  }
 
+  /// Reads data prefixed with a Two Byte Integer length, as used by strings,
+  /// string pairs, and binary data. Returns `Error::Incomplete` with the
+  /// number of missing bytes if the reader ends before the declared length
+  /// was read.
+  fn read_length_prefixed<R: io::Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    // get the expected length of the data
+    let mut length_buffer = [0; 2];
+    reader.read_exact(&mut length_buffer)?;
     let length = u16::from_be_bytes(length_buffer);
 
-    // read the string
+    // read the data
     let mut handle = reader.take(u64::from(length));
     let mut buffer = vec![];
     handle.read_to_end(&mut buffer)?;
-    let s = String::from_utf8(buffer)?;
 
-    Ok(s)
+    // the reader ended before the declared length was read
+    if buffer.len() != usize::from(length) {
+      return Err(Error::Incomplete {
+        needed: Some(usize::from(length) - buffer.len()),
+      });
+    }
+
+    Ok(buffer)
   }
 
   /// Reads bytes from the reader and attempts to convert the bytes to DataType::Utf8EncodedString (String).
@@ -147,6 +345,13 @@ impl DataType {
   /// Unless stated otherwise all UTF-8 encoded strings can have any length in
   /// the range 0 to 65,535 bytes.
   ///
+  /// A UTF-8 encoded sequence 0xEF 0xBB 0xBF is always interpreted as U+FEFF
+  /// ("ZERO WIDTH NO-BREAK SPACE") wherever it appears in a string and MUST NOT
+  /// be skipped over or stripped off by a packet receiver [MQTT-1.5.4-3]. A
+  /// leading BOM is therefore kept as the first character of the string.
+  /// Surrogate code points are invalid UTF-8 and are rejected with
+  /// `Error::ParseError`.
+  ///
   /// # Examples
   ///
   /// ```rust
@@ -213,34 +418,29 @@ impl DataType {
   /// );
   /// ```
   pub fn parse_variable_byte_int<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
-    let mut multiplier: i32 = 1;
-    let mut value: i32 = 0;
+    let mut multiplier: u32 = 1;
+    let mut value: u32 = 0;
 
-    loop {
+    // a variable byte integer is at most four bytes long, so a continuation
+    // bit on the fourth byte is rejected before a fifth byte is read.
+    for position in 1..=4 {
       let mut b = [0; 1];
       reader.read_exact(&mut b)?;
 
-      value += i32::from(b[0] & 127) * multiplier;
+      value += u32::from(b[0] & 127) * multiplier;
+
+      if (b[0] & 128) == 0 {
+        break;
+      }
 
-      if multiplier > (128 * 128 * 128) {
+      if position == 4 {
         return Err(Error::ParseError);
       }
 
       multiplier *= 128;
-
-      if (b[0] & 128) == 0 {
-        break;
-      }
     }
 
-    let parsed = match value {
-      n if n <= 127 => Self::VariableByteInteger(VariableByte::One(u8::try_from(value)?)),
-      n if n <= 16383 => Self::VariableByteInteger(VariableByte::Two(u16::try_from(value)?)),
-      n if n <= 2_097_151 => Self::VariableByteInteger(VariableByte::Three(u32::try_from(value)?)),
-      _ => Self::VariableByteInteger(VariableByte::Four(u32::try_from(value)?)),
-    };
-
-    Ok(parsed)
+    Ok(Self::VariableByteInteger(VariableByte::from(value)))
   }
 
   /// Reads bytes from the reader and attempts to convert the bytes to DataType::BinaryData (Vec<u8>).
@@ -268,17 +468,7 @@ impl DataType {
   /// assert_eq!(result, DataType::BinaryData(expected));
   /// ```
   pub fn parse_binary_data<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
-    // determine the length of the binary data
-    let mut length_buffer = [0; 2];
-    reader.read_exact(&mut length_buffer)?;
-    let length = u16::from_be_bytes(length_buffer);
-
-    // read the data
-    let mut handle = reader.take(u64::from(length));
-    let mut buffer = vec![];
-    handle.read_to_end(&mut buffer)?;
-
-    Ok(Self::BinaryData(buffer))
+    Ok(Self::BinaryData(Self::read_length_prefixed(reader)?))
   }
 
   /// Reads bytes from the reader and attempts to convert the bytes to DataType::Utf8StringPair (String, String).
@@ -294,6 +484,11 @@ impl DataType {
   /// which does not meet these requirements it is a Malformed Packet. Refer to
   /// section 4.13 for information about handling errors.
   ///
+  /// The name and the value are each checked the same way as
+  /// `parse_utf8_string`. The returned error does not say which of the two
+  /// failed; parse the halves separately with `parse_utf8_string` when that
+  /// matters.
+  ///
   /// # Examples
   ///
   /// ```rust
@@ -304,10 +499,10 @@ impl DataType {
   ///   0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 0, 7, 102, 111, 111, 32, 98, 97,
   ///   114, 1, 1, 1, 1,
   /// ];
-
+  ///
   /// let mut reader = io::BufReader::new(&data[..]);
   /// let result = DataType::parse_utf8_string_pair(&mut reader).unwrap();
-
+  ///
   /// assert_eq!(
   ///   result,
   ///   DataType::Utf8StringPair(String::from("hello world"), String::from("foo bar"))
@@ -320,20 +515,152 @@ impl DataType {
     Ok(Self::Utf8StringPair(str_one, str_two))
   }
 
+  /// Parses a value from the start of a slice using one of the `parse_*`
+  /// functions, and returns it along with the number of bytes consumed.
+  /// Returns `Error::Incomplete` if the slice ends before the value does.
+  fn parse_from_slice<'a>(
+    bytes: &'a [u8],
+    kind: DataTypeKind,
+    parse: fn(&mut &'a [u8]) -> Result<Self, Error>,
+  ) -> Result<(Self, usize), Error> {
+    Self::check_complete(bytes, kind)?;
+
+    let mut reader = bytes;
+    let value = parse(&mut reader)?;
+    Ok((value, bytes.len() - reader.len()))
+  }
+
+  /// Returns `Error::Incomplete` if `bytes` is too short to hold a value of
+  /// `kind`, with the number of missing bytes if it is known.
+  fn check_complete(bytes: &[u8], kind: DataTypeKind) -> Result<(), Error> {
+    let required = match kind {
+      DataTypeKind::Byte => 1,
+      DataTypeKind::TwoByteInteger => 2,
+      DataTypeKind::FourByteInteger => 4,
+      DataTypeKind::VariableByteInteger => {
+        // complete once a byte without the continuation bit is seen. if four
+        // bytes have the bit set, let the parser report the error.
+        if bytes.len() < 4 && bytes.iter().all(|byte| byte & 0x80 != 0) {
+          return Err(Error::Incomplete { needed: None });
+        }
+        return Ok(());
+      }
+      DataTypeKind::Utf8EncodedString | DataTypeKind::BinaryData => {
+        Self::length_prefixed_end(bytes, 0)?
+      }
+      DataTypeKind::Utf8StringPair => {
+        let name_end = Self::length_prefixed_end(bytes, 0)?;
+        Self::length_prefixed_end(bytes, name_end)?
+      }
+    };
+
+    if bytes.len() < required {
+      return Err(Error::Incomplete {
+        needed: Some(required - bytes.len()),
+      });
+    }
+
+    Ok(())
+  }
+
+  /// The offset just past length prefixed data starting at `start`. Returns
+  /// `Error::Incomplete` if `bytes` ends before it.
+  fn length_prefixed_end(bytes: &[u8], start: usize) -> Result<usize, Error> {
+    let end = match bytes.get(start..start + 2) {
+      Some(prefix) => start + 2 + usize::from(u16::from_be_bytes([prefix[0], prefix[1]])),
+      None => return Err(Error::Incomplete { needed: None }),
+    };
+
+    if bytes.len() < end {
+      return Err(Error::Incomplete {
+        needed: Some(end - bytes.len()),
+      });
+    }
+
+    Ok(end)
+  }
+
+  /// Same as `parse_byte`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_byte_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(bytes, DataTypeKind::Byte, Self::parse_byte)
+  }
+
+  /// Same as `parse_two_byte_int`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_two_byte_int_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(
+      bytes,
+      DataTypeKind::TwoByteInteger,
+      Self::parse_two_byte_int,
+    )
+  }
+
+  /// Same as `parse_four_byte_int`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_four_byte_int_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(
+      bytes,
+      DataTypeKind::FourByteInteger,
+      Self::parse_four_byte_int,
+    )
+  }
+
+  /// Same as `parse_utf8_string`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_utf8_string_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(
+      bytes,
+      DataTypeKind::Utf8EncodedString,
+      Self::parse_utf8_string,
+    )
+  }
+
+  /// Same as `parse_variable_byte_int`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{DataType, VariableByte};
+  ///
+  /// let data: Vec<u8> = vec![0xFF, 0x7F, 0x01];
+  /// let (value, consumed) = DataType::parse_variable_byte_int_from_slice(&data).unwrap();
+  ///
+  /// assert_eq!(value, DataType::VariableByteInteger(VariableByte::Two(16383)));
+  /// assert_eq!(consumed, 2);
+  /// ```
+  pub fn parse_variable_byte_int_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(
+      bytes,
+      DataTypeKind::VariableByteInteger,
+      Self::parse_variable_byte_int,
+    )
+  }
+
+  /// Same as `parse_binary_data`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_binary_data_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(bytes, DataTypeKind::BinaryData, Self::parse_binary_data)
+  }
+
+  /// Same as `parse_utf8_string_pair`, but reads from a slice and also returns the number
+  /// of bytes consumed.
+  pub fn parse_utf8_string_pair_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    Self::parse_from_slice(
+      bytes,
+      DataTypeKind::Utf8StringPair,
+      Self::parse_utf8_string_pair,
+    )
+  }
+
   /// Used by to_vec() for calculating length for strings, string pairs, and binary data.
   /// The length of arrays is limited to the range of 0 to 65,535 bytes. Because of that we
   /// need to convert usize to a two byte u8 array.
-  fn prepend_length(data: Vec<u8>) -> Result<Vec<u8>, Error> {
-    if data.len() > 65535 {
-      return Err(Error::GenerateError);
-    }
+  fn write_length_prefixed(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let length = u16::try_from(data.len()).map_err(|_| Error::GenerateError)?;
 
-    let length = u16::try_from(data.len() & 0xFFFF)
-      .unwrap()
-      .to_be_bytes()
-      .to_vec();
-
-    Ok([&length[..], &data[..]].concat())
+    Ok([&length.to_be_bytes()[..], data].concat())
   }
 
   /// Used by to_vec() to format variable byte ints into the format defined in the
@@ -386,6 +713,10 @@ impl DataType {
     Ok(bytes)
   }
 
+  /// The number of bytes the value takes up on the wire. Returns
+  /// `Error::ParseError` if that doesn't fit in a u16, e.g. for a string of
+  /// 65,534 bytes or more including its length prefix, or a large string
+  /// pair.
   pub fn byte_len(&self) -> Result<u16, Error> {
     let len = match self {
       Self::Byte(_value) => 1,
@@ -397,12 +728,45 @@ impl DataType {
         VariableByte::Three(_value) => 3,
         VariableByte::Four(_value) => 4,
       },
-      Self::Utf8EncodedString(value) => value.as_bytes().len() + 2,
+      Self::Utf8EncodedString(value) => value.len() + 2,
       Self::BinaryData(value) => value.len() + 2,
-      Self::Utf8StringPair(one, two) => one.as_bytes().len() + two.as_bytes().len() + 4,
+      Self::Utf8StringPair(one, two) => one.len() + two.len() + 4,
     };
 
-    Ok(u16::try_from(len & 0xFFFF)?)
+    Ok(u16::try_from(len)?)
+  }
+
+  /// The number of bytes `to_vec` writes for this value, without generating
+  /// it. Returns `Error::GenerateError` for values `to_vec` can't encode.
+  pub(crate) fn encoded_len(&self) -> Result<u32, Error> {
+    let len = match self {
+      Self::Byte(_value) => 1,
+      Self::TwoByteInteger(_value) => 2,
+      Self::FourByteInteger(_value) => 4,
+      Self::VariableByteInteger(value) => match u32::from(value) {
+        0..=127 => 1,
+        128..=16_383 => 2,
+        16_384..=2_097_151 => 3,
+        2_097_152..=268_435_455 => 4,
+        _ => return Err(Error::GenerateError),
+      },
+      Self::Utf8EncodedString(value) => Self::prefixed_len(value.len())?,
+      Self::BinaryData(value) => Self::prefixed_len(value.len())?,
+      Self::Utf8StringPair(one, two) => {
+        Self::prefixed_len(one.len())? + Self::prefixed_len(two.len())?
+      }
+    };
+
+    Ok(len)
+  }
+
+  /// Length of data written with `write_length_prefixed`.
+  fn prefixed_len(len: usize) -> Result<u32, Error> {
+    if len > 65535 {
+      return Err(Error::GenerateError);
+    }
+
+    Ok(u32::try_from(len)? + 2)
   }
 
   /// Convert DataType variants into u8 vectors.
@@ -412,11 +776,11 @@ impl DataType {
       Self::TwoByteInteger(value) => value.to_be_bytes().to_vec(),
       Self::FourByteInteger(value) => value.to_be_bytes().to_vec(),
       Self::VariableByteInteger(value) => Self::encode_variable_byte(value)?,
-      Self::Utf8EncodedString(value) => Self::prepend_length(value.as_bytes().to_vec())?,
-      Self::BinaryData(value) => Self::prepend_length(value.to_vec())?,
+      Self::Utf8EncodedString(value) => Self::write_length_prefixed(value.as_bytes())?,
+      Self::BinaryData(value) => Self::write_length_prefixed(value)?,
       Self::Utf8StringPair(one, two) => [
-        Self::prepend_length(one.as_bytes().to_vec())?,
-        Self::prepend_length(two.as_bytes().to_vec())?,
+        Self::write_length_prefixed(one.as_bytes())?,
+        Self::write_length_prefixed(two.as_bytes())?,
       ]
       .concat(),
     };
@@ -431,6 +795,60 @@ mod tests {
   use crate::Error;
   use std::io;
 
+  #[test]
+  fn byte_len_overflow() {
+    let string = DataType::Utf8EncodedString("a".repeat(65533));
+    assert_eq!(string.byte_len().unwrap(), 65535);
+
+    let string = DataType::Utf8EncodedString("a".repeat(65534));
+    assert_eq!(string.byte_len().unwrap_err(), Error::ParseError);
+
+    let pair = DataType::Utf8StringPair("a".repeat(40000), "b".repeat(40000));
+    assert_eq!(pair.byte_len().unwrap_err(), Error::ParseError);
+  }
+
+  #[test]
+  fn variable_byte_constructor() {
+    let cases = [
+      (0, VariableByte::One(0)),
+      (127, VariableByte::One(127)),
+      (200, VariableByte::Two(200)),
+      (16_384, VariableByte::Three(16_384)),
+      (2_097_152, VariableByte::Four(2_097_152)),
+      (268_435_455, VariableByte::Four(268_435_455)),
+    ];
+
+    for (value, expected) in cases {
+      assert_eq!(
+        DataType::variable_byte(value).unwrap(),
+        DataType::VariableByteInteger(expected)
+      );
+    }
+
+    assert_ne!(
+      DataType::variable_byte(200).unwrap(),
+      DataType::VariableByteInteger(VariableByte::One(200))
+    );
+    assert_eq!(
+      DataType::variable_byte(268_435_456).unwrap_err(),
+      Error::GenerateError
+    );
+  }
+
+  #[test]
+  fn display() {
+    assert_eq!(DataType::Byte(1).to_string(), "1");
+    assert_eq!(DataType::FourByteInteger(3600).to_string(), "3600");
+    assert_eq!(DataType::from(16_384u32).to_string(), "16384");
+    assert_eq!(DataType::from("a\"b").to_string(), "\"a\\\"b\"");
+    assert_eq!(DataType::BinaryData(vec![0x0A, 0xFF]).to_string(), "0x0aff");
+    assert_eq!(DataType::BinaryData(vec![]).to_string(), "0x");
+    assert_eq!(
+      DataType::Utf8StringPair(String::from("k"), String::from("v")).to_string(),
+      "\"k\": \"v\""
+    );
+  }
+
   #[test]
   fn type_into() {
     let data: Vec<u8> = vec![0x01, 0x02, 0x03];
@@ -440,13 +858,43 @@ mod tests {
     assert_eq!(258, check);
 
     // any other type should return 0 for now
-    let zero = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let zero = [0x01, 0x02, 0x03, 0x04, 0x05];
     reader = io::BufReader::new(&zero[..]);
     let four = DataType::parse_four_byte_int(&mut reader).unwrap();
     check = four.into();
     assert_eq!(0, check);
   }
 
+  #[test]
+  fn variable_byte_from_u32() {
+    assert_eq!(VariableByte::from(127), VariableByte::One(127));
+    assert_eq!(VariableByte::from(128), VariableByte::Two(128));
+    assert_eq!(VariableByte::from(16_384), VariableByte::Three(16_384));
+    assert_eq!(VariableByte::from(2_097_152), VariableByte::Four(2_097_152));
+  }
+
+  #[test]
+  fn from_values() {
+    assert_eq!(DataType::from(255u8), DataType::Byte(255));
+    assert_eq!(DataType::from(258u16), DataType::TwoByteInteger(258));
+    assert_eq!(
+      DataType::from(16_909_060u32),
+      DataType::FourByteInteger(16_909_060)
+    );
+    assert_eq!(
+      DataType::from("hello"),
+      DataType::Utf8EncodedString("hello".to_string())
+    );
+    assert_eq!(
+      DataType::from("hello".to_string()),
+      DataType::Utf8EncodedString("hello".to_string())
+    );
+    assert_eq!(
+      DataType::from(vec![0x01, 0x02]),
+      DataType::BinaryData(vec![0x01, 0x02])
+    );
+  }
+
   #[test]
   fn single_byte() {
     let data: Vec<u8> = vec![0xFF, 0x02];
@@ -481,7 +929,7 @@ mod tests {
       DataType::VariableByteInteger(VariableByte::One(0))
     );
 
-    let max = vec![0x7F];
+    let max = [0x7F];
     reader = io::BufReader::new(&max[..]);
     vari_type = DataType::parse_variable_byte_int(&mut reader).unwrap();
     assert_eq!(
@@ -555,6 +1003,20 @@ mod tests {
     assert_eq!(vari_err, Error::ParseError);
   }
 
+  #[test]
+  fn variable_byte_eof() {
+    let empty: Vec<u8> = vec![];
+    let mut reader = io::BufReader::new(&empty[..]);
+    let err = DataType::parse_variable_byte_int(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: None });
+
+    // a continuation bit followed by the end of the stream
+    let partial: Vec<u8> = vec![0x80];
+    reader = io::BufReader::new(&partial[..]);
+    let err = DataType::parse_variable_byte_int(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: None });
+  }
+
   #[test]
   fn binary_data() {
     let data: Vec<u8> = vec![
@@ -566,6 +1028,14 @@ mod tests {
     assert_eq!(result, DataType::BinaryData(expected));
   }
 
+  #[test]
+  fn binary_data_truncated() {
+    let data: Vec<u8> = vec![0, 10, 0x00, 0x01, 0x02];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_binary_data(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(7) });
+  }
+
   #[test]
   fn string() {
     let data: Vec<u8> = vec![
@@ -580,6 +1050,14 @@ mod tests {
     );
   }
 
+  #[test]
+  fn string_truncated() {
+    let data: Vec<u8> = vec![0, 11, 104, 101, 108, 108, 111];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_utf8_string(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(6) });
+  }
+
   #[test]
   fn string_pair() {
     let data: Vec<u8> = vec![
@@ -596,6 +1074,86 @@ mod tests {
     );
   }
 
+  #[test]
+  fn from_slice() {
+    let byte: Vec<u8> = vec![0xFF, 0x02];
+    let result = DataType::parse_byte_from_slice(&byte).unwrap();
+    assert_eq!(result, (DataType::Byte(255), 1));
+
+    let two: Vec<u8> = vec![0x01, 0x02, 0x03];
+    let result = DataType::parse_two_byte_int_from_slice(&two).unwrap();
+    assert_eq!(result, (DataType::TwoByteInteger(258), 2));
+
+    let four: Vec<u8> = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let result = DataType::parse_four_byte_int_from_slice(&four).unwrap();
+    assert_eq!(result, (DataType::FourByteInteger(16_909_060), 4));
+
+    let vari: Vec<u8> = vec![0x80, 0x80, 0x01, 0x02];
+    let result = DataType::parse_variable_byte_int_from_slice(&vari).unwrap();
+    assert_eq!(
+      result,
+      (DataType::VariableByteInteger(VariableByte::Three(16384)), 3)
+    );
+
+    let string: Vec<u8> = vec![0, 2, 104, 105, 0xFF];
+    let result = DataType::parse_utf8_string_from_slice(&string).unwrap();
+    assert_eq!(result, (DataType::Utf8EncodedString("hi".to_string()), 4));
+
+    let binary: Vec<u8> = vec![0, 2, 0x01, 0x02, 0xFF];
+    let result = DataType::parse_binary_data_from_slice(&binary).unwrap();
+    assert_eq!(result, (DataType::BinaryData(vec![0x01, 0x02]), 4));
+
+    let pair: Vec<u8> = vec![0, 1, 97, 0, 1, 98, 0xFF];
+    let result = DataType::parse_utf8_string_pair_from_slice(&pair).unwrap();
+    assert_eq!(
+      result,
+      (
+        DataType::Utf8StringPair("a".to_string(), "b".to_string()),
+        6
+      )
+    );
+  }
+
+  #[test]
+  fn from_slice_error() {
+    let err = DataType::parse_four_byte_int_from_slice(&[0x01, 0x02]).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(2) });
+
+    let err = DataType::parse_variable_byte_int_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+
+    let err = DataType::parse_utf8_string_from_slice(&[0x00, 0x02, 0xFF, 0xFE]).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+  }
+
+  #[test]
+  fn from_slice_incomplete() {
+    let incomplete = |needed| Error::Incomplete { needed };
+
+    let err = DataType::parse_byte_from_slice(&[]).unwrap_err();
+    assert_eq!(err, incomplete(Some(1)));
+
+    let err = DataType::parse_two_byte_int_from_slice(&[0x01]).unwrap_err();
+    assert_eq!(err, incomplete(Some(1)));
+
+    let err = DataType::parse_variable_byte_int_from_slice(&[0x80, 0x80]).unwrap_err();
+    assert_eq!(err, incomplete(None));
+
+    let err = DataType::parse_utf8_string_from_slice(&[0x00]).unwrap_err();
+    assert_eq!(err, incomplete(None));
+
+    let err = DataType::parse_binary_data_from_slice(&[0x00, 0x03, 0x01]).unwrap_err();
+    assert_eq!(err, incomplete(Some(2)));
+
+    let pair: Vec<u8> = vec![0x00, 0x01, 0x61, 0x00, 0x02, 0x62, 0x63];
+    for end in 0..pair.len() {
+      let err = DataType::parse_utf8_string_pair_from_slice(&pair[..end]).unwrap_err();
+      assert!(matches!(err, Error::Incomplete { .. }));
+    }
+    let err = DataType::parse_utf8_string_pair_from_slice(&pair[..6]).unwrap_err();
+    assert_eq!(err, incomplete(Some(1)));
+  }
+
   #[test]
   fn byte_into_bytes() {
     let value = DataType::Byte(255);
@@ -703,4 +1261,181 @@ mod tests {
     let err = value.to_vec().unwrap_err();
     assert_eq!(err, Error::GenerateError);
   }
+
+  #[test]
+  fn variable_byte_max_length() {
+    let data: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0x7F];
+    let (value, consumed) = DataType::parse_variable_byte_int_from_slice(&data).unwrap();
+    assert_eq!(
+      value,
+      DataType::VariableByteInteger(VariableByte::Four(268_435_455))
+    );
+    assert_eq!(consumed, 4);
+  }
+
+  #[test]
+  fn variable_byte_five_bytes() {
+    let data: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+    let mut reader = &data[..];
+    let err = DataType::parse_variable_byte_int(&mut reader).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+    // the fifth byte is left unread
+    assert_eq!(reader, &[0x01]);
+  }
+
+  #[test]
+  fn ordering() {
+    let mut values = vec![
+      DataType::BinaryData(vec![0x01, 0x02]),
+      DataType::Utf8EncodedString(String::from("b")),
+      DataType::VariableByteInteger(VariableByte::from(128)),
+      DataType::BinaryData(vec![0x01]),
+      DataType::Utf8EncodedString(String::from("a")),
+      DataType::VariableByteInteger(VariableByte::from(1)),
+      DataType::Byte(2),
+      DataType::Byte(1),
+    ];
+    values.sort();
+
+    assert_eq!(
+      values,
+      vec![
+        DataType::Byte(1),
+        DataType::Byte(2),
+        DataType::VariableByteInteger(VariableByte::from(1)),
+        DataType::VariableByteInteger(VariableByte::from(128)),
+        DataType::Utf8EncodedString(String::from("a")),
+        DataType::Utf8EncodedString(String::from("b")),
+        DataType::BinaryData(vec![0x01]),
+        DataType::BinaryData(vec![0x01, 0x02]),
+      ]
+    );
+  }
+
+  #[test]
+  fn string_leading_bom() {
+    let data: Vec<u8> = vec![0x00, 0x05, 0xEF, 0xBB, 0xBF, 0x61, 0x62];
+    let mut reader = io::BufReader::new(&data[..]);
+    let value = DataType::parse_utf8_string(&mut reader).unwrap();
+    assert_eq!(
+      value,
+      DataType::Utf8EncodedString(String::from("\u{FEFF}ab"))
+    );
+    assert_eq!(value.to_vec().unwrap(), data);
+  }
+
+  #[test]
+  fn string_surrogate_error() {
+    // 0xED 0xA0 0x80 would encode the surrogate U+D800
+    let data: Vec<u8> = vec![0x00, 0x03, 0xED, 0xA0, 0x80];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_utf8_string(&mut reader).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+  }
+
+  #[test]
+  fn validated_constructors() {
+    let max = "a".repeat(65535);
+    assert_eq!(
+      DataType::utf8(max.clone()).unwrap(),
+      DataType::Utf8EncodedString(max)
+    );
+    let err = DataType::utf8("a".repeat(65536)).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+
+    assert_eq!(
+      DataType::binary(vec![0; 65535]).unwrap(),
+      DataType::BinaryData(vec![0; 65535])
+    );
+    let err = DataType::binary(vec![0; 65536]).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+  }
+
+  #[test]
+  fn encoded_len() {
+    let values = [
+      DataType::Byte(1),
+      DataType::TwoByteInteger(1),
+      DataType::FourByteInteger(1),
+      DataType::VariableByteInteger(VariableByte::from(127)),
+      DataType::VariableByteInteger(VariableByte::from(268_435_455)),
+      DataType::Utf8EncodedString(String::from("test")),
+      DataType::BinaryData(vec![0x01, 0x02]),
+      DataType::Utf8StringPair("a".repeat(40_000), "b".repeat(40_000)),
+    ];
+
+    for value in values.iter() {
+      let len = value.to_vec().unwrap().len();
+      assert_eq!(value.encoded_len().unwrap() as usize, len);
+    }
+
+    let err = DataType::BinaryData(vec![0; 65536])
+      .encoded_len()
+      .unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+  }
+
+  #[test]
+  fn hash() {
+    use std::collections::HashSet;
+
+    let mut set = HashSet::new();
+    set.insert(DataType::VariableByteInteger(VariableByte::from(321)));
+    set.insert(DataType::Utf8StringPair(
+      String::from("a"),
+      String::from("b"),
+    ));
+    set.insert(DataType::VariableByteInteger(VariableByte::from(321)));
+
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&DataType::VariableByteInteger(VariableByte::Two(321))));
+  }
+
+  #[test]
+  fn string_pair_invalid_name() {
+    let data: Vec<u8> = vec![0x00, 0x01, 0xFF, 0x00, 0x01, 0x61];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_utf8_string_pair(&mut reader).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+  }
+
+  #[test]
+  fn string_pair_invalid_value() {
+    let data: Vec<u8> = vec![0x00, 0x01, 0x61, 0x00, 0x01, 0xFF];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_utf8_string_pair(&mut reader).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+
+    // the value is shorter than its declared length
+    let data: Vec<u8> = vec![0x00, 0x01, 0x61, 0x00, 0x02, 0x62];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = DataType::parse_utf8_string_pair(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(1) });
+  }
+
+  #[test]
+  fn length_prefixed() {
+    let bytes = DataType::write_length_prefixed(&[0xAA, 0xBB]).unwrap();
+    assert_eq!(bytes, vec![0x00, 0x02, 0xAA, 0xBB]);
+
+    let mut reader = &bytes[..];
+    assert_eq!(
+      DataType::read_length_prefixed(&mut reader).unwrap(),
+      vec![0xAA, 0xBB]
+    );
+
+    let mut reader = &bytes[..3];
+    let err = DataType::read_length_prefixed(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(1) });
+
+    let mut reader = &bytes[..1];
+    let err = DataType::read_length_prefixed(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: None });
+
+    let empty = DataType::write_length_prefixed(&[]).unwrap();
+    assert_eq!(empty, vec![0x00, 0x00]);
+
+    let err = DataType::write_length_prefixed(&[0; 65536]).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+  }
 }
@@ -0,0 +1,60 @@
+/// Format bytes as an `xxd` style hexdump with offset, hex, and ASCII columns.
+///
+/// Useful for comparing generated bytes with the output of another
+/// implementation.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::debug_bytes;
+///
+/// let bytes: Vec<u8> = vec![0x03, 0x00, 0x04, 0x74, 0x65, 0x73, 0x74];
+/// assert_eq!(
+///   debug_bytes(&bytes),
+///   "00000000: 0300 0474 6573 74                        ...test"
+/// );
+/// ```
+pub fn debug_bytes(bytes: &[u8]) -> String {
+  let lines: Vec<String> = bytes
+    .chunks(16)
+    .enumerate()
+    .map(|(index, chunk)| {
+      let hex: Vec<String> = chunk
+        .chunks(2)
+        .map(|pair| pair.iter().map(|b| format!("{:02x}", b)).collect())
+        .collect();
+
+      let ascii: String = chunk
+        .iter()
+        .map(|b| match b {
+          0x20..=0x7E => char::from(*b),
+          _ => '.',
+        })
+        .collect();
+
+      format!("{:08x}: {:<40} {}", index * 16, hex.join(" "), ascii)
+    })
+    .collect();
+
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::debug_bytes;
+
+  #[test]
+  fn empty() {
+    assert_eq!(debug_bytes(&[]), "");
+  }
+
+  #[test]
+  fn multiple_lines() {
+    let bytes: Vec<u8> = (0x40..0x52).collect();
+    assert_eq!(
+      debug_bytes(&bytes),
+      "00000000: 4041 4243 4445 4647 4849 4a4b 4c4d 4e4f  @ABCDEFGHIJKLMNO\n\
+       00000010: 5051                                     PQ"
+    );
+  }
+}
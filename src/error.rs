@@ -1,6 +1,6 @@
 use std::error::Error as StdError;
 use std::fmt;
-use std::io::Error as IoError;
+use std::io::{Error as IoError, ErrorKind};
 use std::num::{ParseIntError, TryFromIntError};
 use std::string::FromUtf8Error;
 
@@ -9,7 +9,26 @@ use std::string::FromUtf8Error;
 pub enum Error {
   ParseError,
   GenerateError,
+  /// The packet could not be parsed according to the spec, e.g. a property
+  /// that isn't valid for the packet type, or a repeated property that may
+  /// only appear once. Maps to Reason Code 0x81 (Malformed Packet).
   MalformedPacket,
+  /// The packet was parsed but breaks a protocol rule, e.g. a Subscription
+  /// Identifier or Topic Alias of 0. Maps to Reason Code 0x82 (Protocol Error).
+  ProtocolError,
+  /// The declared Remaining Length is larger than the caller allows. Maps to
+  /// Reason Code 0x95 (Packet too large).
+  PacketTooLarge,
+  /// The CONNECT Protocol Version is not supported. Maps to Reason Code 0x84
+  /// (Unsupported Protocol Version).
+  UnsupportedProtocolVersion,
+  /// The buffer or reader ends before the data being parsed is complete.
+  /// Unlike the other errors this is not a problem with the packet: a
+  /// streaming parser should read more bytes and try again. `needed` is the
+  /// number of additional bytes required, if known.
+  Incomplete {
+    needed: Option<usize>,
+  },
 }
 
 impl StdError for Error {
@@ -18,6 +37,10 @@ impl StdError for Error {
       Error::ParseError => "Unable to parse type",
       Error::GenerateError => "Unable to generate data",
       Error::MalformedPacket => "Malformed packet",
+      Error::ProtocolError => "Protocol error",
+      Error::PacketTooLarge => "Packet too large",
+      Error::UnsupportedProtocolVersion => "Unsupported protocol version",
+      Error::Incomplete { .. } => "Incomplete data",
     }
   }
 }
@@ -28,38 +51,43 @@ impl fmt::Display for Error {
       Error::ParseError => f.write_str("ParseError"),
       Error::GenerateError => f.write_str("GenerateError"),
       Error::MalformedPacket => f.write_str("MalformedPacket"),
+      Error::ProtocolError => f.write_str("ProtocolError"),
+      Error::PacketTooLarge => f.write_str("PacketTooLarge"),
+      Error::UnsupportedProtocolVersion => f.write_str("UnsupportedProtocolVersion"),
+      Error::Incomplete { needed: None } => f.write_str("Incomplete"),
+      Error::Incomplete {
+        needed: Some(needed),
+      } => write!(f, "Incomplete ({} more bytes needed)", needed),
     }
   }
 }
 
+/// A reader that ends early means more bytes are needed, not that the bytes
+/// read so far are invalid, so `UnexpectedEof` becomes `Error::Incomplete`.
+/// Any other io error is a `ParseError`.
 impl From<IoError> for Error {
   fn from(e: IoError) -> Self {
-    match e {
+    match e.kind() {
+      ErrorKind::UnexpectedEof => Error::Incomplete { needed: None },
       _ => Error::ParseError,
     }
   }
 }
 
 impl From<FromUtf8Error> for Error {
-  fn from(e: FromUtf8Error) -> Self {
-    match e {
-      _ => Error::ParseError,
-    }
+  fn from(_e: FromUtf8Error) -> Self {
+    Error::ParseError
   }
 }
 
 impl From<TryFromIntError> for Error {
-  fn from(e: TryFromIntError) -> Self {
-    match e {
-      _ => Error::ParseError,
-    }
+  fn from(_e: TryFromIntError) -> Self {
+    Error::ParseError
   }
 }
 
 impl From<ParseIntError> for Error {
-  fn from(e: ParseIntError) -> Self {
-    match e {
-      _ => Error::ParseError,
-    }
+  fn from(_e: ParseIntError) -> Self {
+    Error::ParseError
   }
 }
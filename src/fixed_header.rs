@@ -0,0 +1,402 @@
+use crate::Error;
+use crate::{DataType, VariableByte};
+use crate::{Flags, PacketType};
+use std::convert::TryFrom;
+use std::io;
+
+/// [2.1.1 Fixed Header](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901021)
+///
+/// Each MQTT Control Packet contains a Fixed Header: the packet type and flags
+/// in the first byte, followed by the Remaining Length.
+#[derive(Debug, PartialEq)]
+pub struct FixedHeader {
+  flags: Flags,
+  remaining_length: u32,
+}
+
+impl FixedHeader {
+  /// Parse the fixed header from a reader.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{FixedHeader, PacketType};
+  /// use std::io;
+  ///
+  /// let data: Vec<u8> = vec![0x82, 0xC1, 0x02];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let header = FixedHeader::new(&mut reader).unwrap();
+  ///
+  /// assert_eq!(header.packet_type(), PacketType::SUBSCRIBE);
+  /// assert_eq!(header.remaining_length(), 321);
+  /// ```
+  pub fn new<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
+    Self::new_bounded(reader, 268_435_455)
+  }
+
+  /// Same as `new`, but returns `Error::PacketTooLarge` if the Remaining
+  /// Length is larger than `max_remaining`. This lets the caller reject a
+  /// packet before reading or allocating space for the rest of it, e.g. when
+  /// a smaller Maximum Packet Size was negotiated with a client.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, FixedHeader};
+  /// use std::io;
+  ///
+  /// let data: Vec<u8> = vec![0x30, 0x80, 0x01];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let err = FixedHeader::new_bounded(&mut reader, 127).unwrap_err();
+  ///
+  /// assert_eq!(err, Error::PacketTooLarge);
+  /// ```
+  pub fn new_bounded<R: io::Read>(reader: &mut R, max_remaining: u32) -> Result<Self, Error> {
+    let mut header = [0; 1];
+    reader.read_exact(&mut header)?;
+
+    let flags = Flags::new(header[0])?;
+    let remaining_length = decode_remaining_length(reader)?;
+
+    if remaining_length > max_remaining {
+      return Err(Error::PacketTooLarge);
+    }
+
+    Ok(Self {
+      flags,
+      remaining_length,
+    })
+  }
+
+  /// Parse the fixed header from the start of a buffer that may not hold the
+  /// whole header yet, e.g. when reading from a non-blocking socket. Returns
+  /// the header and the number of bytes it used, or `Error::Incomplete` if
+  /// the buffer ends before the Remaining Length does.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, FixedHeader};
+  ///
+  /// let err = FixedHeader::from_slice(&[0x30, 0x80]).unwrap_err();
+  /// assert_eq!(err, Error::Incomplete { needed: None });
+  ///
+  /// let (header, consumed) = FixedHeader::from_slice(&[0x30, 0x80, 0x01]).unwrap();
+  /// assert_eq!(header.remaining_length(), 128);
+  /// assert_eq!(consumed, 3);
+  /// ```
+  pub fn from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    // an invalid first byte is an error no matter what follows it
+    if let Some(header) = bytes.first() {
+      Flags::new(*header)?;
+    }
+
+    // the Remaining Length is complete once a byte without the continuation
+    // bit is seen. if four bytes have continuation bits set, let `new` report
+    // the error instead of waiting for more data.
+    let complete = bytes[1.min(bytes.len())..]
+      .iter()
+      .take(4)
+      .any(|byte| byte & 0x80 == 0);
+
+    if !complete && bytes.len() < 5 {
+      return Err(Error::Incomplete { needed: None });
+    }
+
+    let mut reader = bytes;
+    let header = Self::new(&mut reader)?;
+    Ok((header, bytes.len() - reader.len()))
+  }
+
+  /// Return the packet body, i.e. the first `remaining_length` bytes of
+  /// `bytes`, where `bytes` holds everything after the fixed header. Returns
+  /// `Error::Incomplete` with the number of missing bytes if `bytes` is
+  /// shorter than the Remaining Length.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, FixedHeader};
+  ///
+  /// let data: Vec<u8> = vec![0x40, 0x02, 0x00];
+  /// let (header, consumed) = FixedHeader::from_slice(&data).unwrap();
+  ///
+  /// let err = header.body(&data[consumed..]).unwrap_err();
+  /// assert_eq!(err, Error::Incomplete { needed: Some(1) });
+  /// ```
+  pub fn body<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], Error> {
+    let length = usize::try_from(self.remaining_length).map_err(|_| Error::ParseError)?;
+
+    if bytes.len() < length {
+      return Err(Error::Incomplete {
+        needed: Some(length - bytes.len()),
+      });
+    }
+
+    Ok(&bytes[..length])
+  }
+
+  /// The packet type from bits 7-4 of the first byte.
+  pub fn packet_type(&self) -> PacketType {
+    self.flags.packet_type()
+  }
+
+  /// The flags from bits 3-0 of the first byte.
+  pub fn flags(&self) -> &Flags {
+    &self.flags
+  }
+
+  /// The Remaining Length as declared on the wire. Comparing it against the
+  /// number of bytes actually consumed is useful when debugging interop issues.
+  pub fn remaining_length(&self) -> u32 {
+    self.remaining_length
+  }
+}
+
+/// Encode the Remaining Length field of the fixed header.
+///
+/// [2.1.4 Remaining Length](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901024)
+///
+/// The Remaining Length is a Variable Byte Integer that represents the number
+/// of bytes remaining within the current Control Packet, including data in the
+/// Variable Header and the Payload. The Remaining Length does not include the
+/// bytes used to encode the Remaining Length.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::encode_remaining_length;
+///
+/// assert_eq!(encode_remaining_length(321).unwrap(), vec![0xC1, 0x02]);
+/// ```
+pub fn encode_remaining_length(len: u32) -> Result<Vec<u8>, Error> {
+  DataType::VariableByteInteger(VariableByte::from(len)).to_vec()
+}
+
+/// Prepend the fixed header to a packet body, i.e. the variable header and
+/// payload, and return the complete packet.
+///
+/// The first byte holds `packet_type` in bits 7-4 and `flags` in bits 3-0,
+/// followed by the length of `body` encoded as the Remaining Length. Returns
+/// `Error::GenerateError` if `flags` does not fit in four bits, is not valid
+/// for `packet_type` (e.g. reserved flags other than 0x02 for PUBREL), or
+/// `body` is too long to encode.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::{frame, PacketType};
+///
+/// let packet = frame(PacketType::PUBACK, 0x00, vec![0x00, 0x01]).unwrap();
+/// assert_eq!(packet, vec![0x40, 0x02, 0x00, 0x01]);
+/// ```
+pub fn frame(packet_type: PacketType, flags: u8, body: Vec<u8>) -> Result<Vec<u8>, Error> {
+  if flags > 0x0F {
+    return Err(Error::GenerateError);
+  }
+
+  // parse the flags and write them back out, so reserved flags that don't
+  // have the value the spec requires are rejected
+  let header = (u8::from(packet_type) << 4) | flags;
+  let written = Flags::new(header)
+    .and_then(|parsed| parsed.to_header_byte(packet_type))
+    .map_err(|_| Error::GenerateError)?;
+
+  if written != header {
+    return Err(Error::GenerateError);
+  }
+
+  let length = u32::try_from(body.len()).map_err(|_| Error::GenerateError)?;
+
+  let mut packet = vec![header];
+  packet.extend(encode_remaining_length(length)?);
+  packet.extend(body);
+
+  Ok(packet)
+}
+
+/// Decode the Remaining Length field of the fixed header from a reader.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::decode_remaining_length;
+/// use std::io;
+///
+/// let data: Vec<u8> = vec![0xC1, 0x02];
+/// let mut reader = io::BufReader::new(&data[..]);
+/// assert_eq!(decode_remaining_length(&mut reader).unwrap(), 321);
+/// ```
+pub fn decode_remaining_length<R: io::Read>(reader: &mut R) -> Result<u32, Error> {
+  match DataType::parse_variable_byte_int(reader)? {
+    DataType::VariableByteInteger(value) => Ok(u32::from(&value)),
+    _ => Err(Error::ParseError),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode_remaining_length, encode_remaining_length, frame, FixedHeader};
+  use crate::{Error, PacketType};
+  use std::io;
+
+  fn round_trip(len: u32, expected: Vec<u8>) {
+    let bytes = encode_remaining_length(len).unwrap();
+    assert_eq!(bytes, expected);
+
+    let mut reader = io::BufReader::new(&bytes[..]);
+    assert_eq!(decode_remaining_length(&mut reader).unwrap(), len);
+  }
+
+  #[test]
+  fn one_byte() {
+    round_trip(0, vec![0x00]);
+    round_trip(127, vec![0x7F]);
+  }
+
+  #[test]
+  fn two_bytes() {
+    round_trip(128, vec![0x80, 0x01]);
+    round_trip(16_383, vec![0xFF, 0x7F]);
+  }
+
+  #[test]
+  fn three_bytes() {
+    round_trip(16_384, vec![0x80, 0x80, 0x01]);
+    round_trip(2_097_151, vec![0xFF, 0xFF, 0x7F]);
+  }
+
+  #[test]
+  fn four_bytes() {
+    round_trip(2_097_152, vec![0x80, 0x80, 0x80, 0x01]);
+    round_trip(268_435_455, vec![0xFF, 0xFF, 0xFF, 0x7F]);
+  }
+
+  #[test]
+  fn encode_error() {
+    let err = encode_remaining_length(268_435_456).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+  }
+
+  #[test]
+  fn decode_error() {
+    let data: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFF];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = decode_remaining_length(&mut reader).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+  }
+
+  #[test]
+  fn fixed_header() {
+    let data: Vec<u8> = vec![0x3D, 0x80, 0x01];
+    let mut reader = io::BufReader::new(&data[..]);
+    let header = FixedHeader::new(&mut reader).unwrap();
+    assert_eq!(header.packet_type(), PacketType::PUBLISH);
+    assert_eq!(header.flags().to_u8().unwrap(), 0x0D);
+    assert_eq!(header.remaining_length(), 128);
+  }
+
+  #[test]
+  fn fixed_header_error() {
+    let data: Vec<u8> = vec![0x80, 0x00];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = FixedHeader::new(&mut reader).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+  }
+
+  #[test]
+  fn fixed_header_bounded() {
+    let data: Vec<u8> = vec![0xC0, 0x80, 0x01];
+
+    let mut reader = io::BufReader::new(&data[..]);
+    let header = FixedHeader::new_bounded(&mut reader, 128).unwrap();
+    assert_eq!(header.remaining_length(), 128);
+
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = FixedHeader::new_bounded(&mut reader, 127).unwrap_err();
+    assert_eq!(err, Error::PacketTooLarge);
+  }
+
+  #[test]
+  fn from_slice_partial() {
+    let data: Vec<u8> = vec![0x30, 0x80, 0x80, 0x80, 0x01];
+
+    for end in 0..data.len() {
+      let err = FixedHeader::from_slice(&data[..end]).unwrap_err();
+      assert_eq!(err, Error::Incomplete { needed: None });
+    }
+
+    let (header, consumed) = FixedHeader::from_slice(&data).unwrap();
+    assert_eq!(header.packet_type(), PacketType::PUBLISH);
+    assert_eq!(header.remaining_length(), 2_097_152);
+    assert_eq!(consumed, 5);
+  }
+
+  #[test]
+  fn from_slice_error() {
+    let err = FixedHeader::from_slice(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+
+    let err = FixedHeader::from_slice(&[0x80, 0x00]).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+
+    let err = FixedHeader::from_slice(&[0x00]).unwrap_err();
+    assert_eq!(err, Error::ParseError);
+
+    let err = FixedHeader::from_slice(&[0x36]).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+  }
+
+  #[test]
+  fn body() {
+    let data: Vec<u8> = vec![0x62, 0x02, 0x00, 0x01, 0xE0];
+    let (header, consumed) = FixedHeader::from_slice(&data).unwrap();
+    assert_eq!(header.body(&data[consumed..]).unwrap(), &[0x00, 0x01]);
+
+    let err = header.body(&data[consumed..consumed + 1]).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(1) });
+
+    let err = header.body(&[]).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: Some(2) });
+  }
+
+  #[test]
+  fn frame_multi_byte_length() {
+    let body = vec![0xAB; 200];
+    let packet = frame(PacketType::PUBLISH, 0x0B, body.clone()).unwrap();
+    assert_eq!(&packet[..3], &[0x3B, 0xC8, 0x01]);
+    assert_eq!(&packet[3..], &body[..]);
+
+    let mut reader = io::BufReader::new(&packet[..]);
+    let header = FixedHeader::new(&mut reader).unwrap();
+    assert_eq!(header.packet_type(), PacketType::PUBLISH);
+    assert_eq!(header.remaining_length(), 200);
+  }
+
+  #[test]
+  fn frame_error() {
+    let err = frame(PacketType::PUBLISH, 0x10, vec![]).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+
+    // reserved flags must be 0x02 for PUBREL, SUBSCRIBE and UNSUBSCRIBE
+    for packet_type in [
+      PacketType::PUBREL,
+      PacketType::SUBSCRIBE,
+      PacketType::UNSUBSCRIBE,
+    ] {
+      let err = frame(packet_type, 0x00, vec![]).unwrap_err();
+      assert_eq!(err, Error::GenerateError);
+
+      let packet = frame(packet_type, 0x02, vec![]).unwrap();
+      assert_eq!(packet[0], (u8::from(packet_type) << 4) | 0x02);
+    }
+
+    // and 0 for the other packet types
+    let err = frame(PacketType::CONNECT, 0x01, vec![]).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+
+    // QoS 3 is not a valid PUBLISH flag
+    let err = frame(PacketType::PUBLISH, 0x06, vec![]).unwrap_err();
+    assert_eq!(err, Error::GenerateError);
+  }
+}
@@ -1,17 +1,70 @@
 use crate::Error;
-use crate::PacketType;
+use crate::{PacketType, Qos};
 use std::convert::TryFrom;
 
+/// Flag bits 0-3 of a non-PUBLISH packet, along with the packet type they
+/// were parsed for.
 #[derive(Debug, PartialEq, Eq)]
-pub struct GenericFlags(bool, bool, bool, bool);
+pub struct GenericFlags(bool, bool, bool, bool, PacketType);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct PublishFlags {
   retain: bool,
   qos: u8,
   dup: bool,
 }
 
+impl PublishFlags {
+  /// Build PUBLISH flags from their parts. The counterpart to parsing them
+  /// from a header byte with `Flags::new`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Flags, PacketType, PublishFlags, Qos};
+  ///
+  /// let flags = Flags::Publish(PublishFlags::from_parts(Qos::ExactlyOnce, true, true));
+  /// assert_eq!(flags.to_header_byte(PacketType::PUBLISH).unwrap(), 0x3D);
+  /// assert_eq!(Flags::new(0x3D).unwrap(), flags);
+  /// ```
+  pub fn from_parts(qos: Qos, dup: bool, retain: bool) -> Self {
+    Self {
+      retain,
+      qos: u8::from(qos),
+      dup,
+    }
+  }
+
+  /// The flags as a `(retain, qos, dup)` tuple.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::Flags;
+  ///
+  /// let flags = Flags::new(0x3B).unwrap();
+  /// assert_eq!(flags.publish().unwrap().as_tuple(), (true, 1, true));
+  /// ```
+  pub fn as_tuple(&self) -> (bool, u8, bool) {
+    (self.retain, self.qos, self.dup)
+  }
+
+  /// QoS 0 flags with retain and dup unset.
+  pub fn at_most_once() -> Self {
+    Self::from_parts(Qos::AtMostOnce, false, false)
+  }
+
+  /// QoS 1 flags with retain and dup unset.
+  pub fn at_least_once() -> Self {
+    Self::from_parts(Qos::AtLeastOnce, false, false)
+  }
+
+  /// QoS 2 flags with retain and dup unset.
+  pub fn exactly_once() -> Self {
+    Self::from_parts(Qos::ExactlyOnce, false, false)
+  }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Flags {
   Generic(GenericFlags),
@@ -27,6 +80,7 @@ impl Flags {
       (header & 0x02) == 0x02,
       (header & 0x04) == 0x04,
       (header & 0x08) == 0x08,
+      packet_type,
     ));
 
     match packet_type {
@@ -64,6 +118,30 @@ impl Flags {
     }
   }
 
+  /// The inner PublishFlags, or `None` for any other packet type.
+  pub fn publish(&self) -> Option<&PublishFlags> {
+    match self {
+      Flags::Publish(value) => Some(value),
+      Flags::Generic(_) => None,
+    }
+  }
+
+  /// The inner GenericFlags, or `None` for PUBLISH.
+  pub fn generic(&self) -> Option<&GenericFlags> {
+    match self {
+      Flags::Generic(value) => Some(value),
+      Flags::Publish(_) => None,
+    }
+  }
+
+  /// The packet type the flags belong to.
+  pub fn packet_type(&self) -> PacketType {
+    match self {
+      Flags::Publish(_) => PacketType::PUBLISH,
+      Flags::Generic(value) => value.4,
+    }
+  }
+
   /// Convert Flag variants into u8.
   pub fn to_u8(&self) -> Result<u8, Error> {
     let mut flag: u8 = 0x00;
@@ -96,10 +174,61 @@ impl Flags {
 
     Ok(flag)
   }
+
+  /// Generate the first byte of the fixed header, with the packet type in bits
+  /// 7-4 and the flags in bits 3-0.
+  ///
+  /// Reserved flags are always written with the value required by the spec,
+  /// e.g. 0x02 for SUBSCRIBE. Returns `Error::GenerateError` if `packet_type`
+  /// doesn't match the packet type of the flags, or if generic flags are used
+  /// for PUBLISH.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Flags, PacketType};
+  ///
+  /// let flags = Flags::new(0x3D).unwrap();
+  /// assert_eq!(flags.to_header_byte(PacketType::PUBLISH).unwrap(), 0x3D);
+  /// ```
+  pub fn to_header_byte(&self, packet_type: PacketType) -> Result<u8, Error> {
+    if self.packet_type() != packet_type {
+      return Err(Error::GenerateError);
+    }
+
+    let flags = match (self, packet_type) {
+      (Flags::Publish(value), PacketType::PUBLISH) => {
+        if value.qos > 2 {
+          return Err(Error::GenerateError);
+        }
+        self.to_u8()?
+      }
+      (Flags::Publish(_), _) | (Flags::Generic(_), PacketType::PUBLISH) => {
+        return Err(Error::GenerateError)
+      }
+      (Flags::Generic(_), PacketType::PUBREL)
+      | (Flags::Generic(_), PacketType::SUBSCRIBE)
+      | (Flags::Generic(_), PacketType::UNSUBSCRIBE) => 0x02,
+      (Flags::Generic(_), _) => 0x00,
+    };
+
+    Ok((u8::from(packet_type) << 4) | flags)
+  }
+}
+
+impl TryFrom<u8> for Flags {
+  type Error = Error;
+
+  fn try_from(header: u8) -> Result<Self, Error> {
+    Self::new(header)
+  }
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::PacketType;
+  use std::convert::TryFrom;
+
   #[test]
   fn publish() {
     let fixed_header: u8 = 0x3D;
@@ -127,7 +256,13 @@ mod tests {
     let flag_type = super::Flags::new(fixed_header);
     assert_eq!(
       flag_type.unwrap(),
-      super::Flags::Generic(super::GenericFlags(true, true, true, true))
+      super::Flags::Generic(super::GenericFlags(
+        true,
+        true,
+        true,
+        true,
+        PacketType::CONNECT
+      ))
     );
   }
 
@@ -137,7 +272,13 @@ mod tests {
     let flag_type = super::Flags::new(fixed_header);
     assert_eq!(
       flag_type.unwrap(),
-      super::Flags::Generic(super::GenericFlags(true, false, false, false))
+      super::Flags::Generic(super::GenericFlags(
+        true,
+        false,
+        false,
+        false,
+        PacketType::CONNACK
+      ))
     );
   }
 
@@ -147,7 +288,13 @@ mod tests {
     let flag_type = super::Flags::new(fixed_header);
     assert_eq!(
       flag_type.unwrap(),
-      super::Flags::Generic(super::GenericFlags(false, true, false, false))
+      super::Flags::Generic(super::GenericFlags(
+        false,
+        true,
+        false,
+        false,
+        PacketType::PUBREL
+      ))
     );
   }
 
@@ -157,7 +304,13 @@ mod tests {
     let flag_type = super::Flags::new(fixed_header);
     assert_eq!(
       flag_type.unwrap(),
-      super::Flags::Generic(super::GenericFlags(false, true, false, false))
+      super::Flags::Generic(super::GenericFlags(
+        false,
+        true,
+        false,
+        false,
+        PacketType::SUBSCRIBE
+      ))
     );
   }
 
@@ -167,7 +320,13 @@ mod tests {
     let flag_type = super::Flags::new(fixed_header);
     assert_eq!(
       flag_type.unwrap(),
-      super::Flags::Generic(super::GenericFlags(false, true, false, false))
+      super::Flags::Generic(super::GenericFlags(
+        false,
+        true,
+        false,
+        false,
+        PacketType::UNSUBSCRIBE
+      ))
     );
   }
 
@@ -200,25 +359,161 @@ mod tests {
 
   #[test]
   fn generic_one_to_u8() {
-    let flag_type = super::Flags::Generic(super::GenericFlags(true, false, false, false));
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      true,
+      false,
+      false,
+      false,
+      PacketType::CONNECT,
+    ));
     assert_eq!(flag_type.to_u8().unwrap(), 0x01);
   }
 
   #[test]
   fn generic_two_to_u8() {
-    let flag_type = super::Flags::Generic(super::GenericFlags(true, true, false, false));
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      true,
+      true,
+      false,
+      false,
+      PacketType::CONNECT,
+    ));
     assert_eq!(flag_type.to_u8().unwrap(), 0x03);
   }
 
   #[test]
   fn generic_three_to_u8() {
-    let flag_type = super::Flags::Generic(super::GenericFlags(true, true, true, false));
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      true,
+      true,
+      true,
+      false,
+      PacketType::CONNECT,
+    ));
     assert_eq!(flag_type.to_u8().unwrap(), 0x07);
   }
 
   #[test]
   fn generic_four_to_u8() {
-    let flag_type = super::Flags::Generic(super::GenericFlags(true, true, true, true));
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      true,
+      true,
+      true,
+      true,
+      PacketType::CONNECT,
+    ));
     assert_eq!(flag_type.to_u8().unwrap(), 0x0F);
   }
+
+  #[test]
+  fn packet_type() {
+    let publish = super::Flags::try_from(0x3D).unwrap();
+    assert_eq!(publish.packet_type(), PacketType::PUBLISH);
+
+    let subscribe = super::Flags::try_from(0x82).unwrap();
+    assert_eq!(subscribe.packet_type(), PacketType::SUBSCRIBE);
+
+    let err = super::Flags::try_from(0xAF).unwrap_err();
+    assert_eq!(err, crate::Error::MalformedPacket);
+  }
+
+  #[test]
+  fn publish_header_byte() {
+    let flag_type = super::Flags::Publish(super::PublishFlags {
+      retain: true,
+      qos: 2,
+      dup: true,
+    });
+    assert_eq!(flag_type.to_header_byte(PacketType::PUBLISH).unwrap(), 0x3D);
+
+    let err = flag_type.to_header_byte(PacketType::CONNECT).unwrap_err();
+    assert_eq!(err, crate::Error::GenerateError);
+  }
+
+  #[test]
+  fn subscribe_header_byte() {
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      false,
+      false,
+      false,
+      false,
+      PacketType::SUBSCRIBE,
+    ));
+    assert_eq!(
+      flag_type.to_header_byte(PacketType::SUBSCRIBE).unwrap(),
+      0x82
+    );
+
+    let err = flag_type.to_header_byte(PacketType::PUBLISH).unwrap_err();
+    assert_eq!(err, crate::Error::GenerateError);
+  }
+
+  #[test]
+  fn generic_header_byte() {
+    let flag_type = super::Flags::new(0x10).unwrap();
+    assert_eq!(flag_type.to_header_byte(PacketType::CONNECT).unwrap(), 0x10);
+
+    // flags parsed for CONNECT can't be written as another packet type
+    let err = flag_type.to_header_byte(PacketType::SUBSCRIBE).unwrap_err();
+    assert_eq!(err, crate::Error::GenerateError);
+  }
+
+  #[test]
+  fn generic_publish_header_byte() {
+    let flag_type = super::Flags::Generic(super::GenericFlags(
+      false,
+      false,
+      false,
+      false,
+      PacketType::PUBLISH,
+    ));
+    let err = flag_type.to_header_byte(PacketType::PUBLISH).unwrap_err();
+    assert_eq!(err, crate::Error::GenerateError);
+  }
+
+  #[test]
+  fn publish_presets() {
+    let default = super::Flags::Publish(super::PublishFlags::default());
+    assert_eq!(default.to_u8().unwrap(), 0x00);
+
+    let qos_0 = super::Flags::Publish(super::PublishFlags::at_most_once());
+    assert_eq!(qos_0.to_u8().unwrap(), 0x00);
+
+    let qos_1 = super::Flags::Publish(super::PublishFlags::at_least_once());
+    assert_eq!(qos_1.to_u8().unwrap(), 0x02);
+
+    let qos_2 = super::Flags::Publish(super::PublishFlags::exactly_once());
+    assert_eq!(qos_2.to_u8().unwrap(), 0x04);
+  }
+
+  #[test]
+  fn accessors() {
+    let publish = super::Flags::new(0x3D).unwrap();
+    assert_eq!(publish.publish().unwrap().as_tuple(), (true, 2, true));
+    assert_eq!(publish.generic(), None);
+
+    let subscribe = super::Flags::new(0x82).unwrap();
+    assert_eq!(subscribe.publish(), None);
+    assert_eq!(
+      subscribe.generic(),
+      Some(&super::GenericFlags(
+        false,
+        true,
+        false,
+        false,
+        PacketType::SUBSCRIBE
+      ))
+    );
+  }
+
+  #[test]
+  fn publish_from_parts() {
+    let flags = super::PublishFlags::from_parts(crate::Qos::AtLeastOnce, false, true);
+    assert_eq!(flags.as_tuple(), (true, 1, false));
+
+    let flags = super::Flags::Publish(flags);
+    let header = flags.to_header_byte(PacketType::PUBLISH).unwrap();
+    assert_eq!(header, 0x33);
+    assert_eq!(super::Flags::new(header).unwrap(), flags);
+  }
 }
@@ -2,15 +2,29 @@
 //!
 //! [mqtt]: https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html
 
+mod client_id;
 mod data_type;
+#[cfg(feature = "debug")]
+mod debug;
 mod error;
+mod fixed_header;
 mod flags;
 mod macros;
 mod packet_type;
 mod property;
+mod protocol_version;
+mod qos;
+mod storage;
+pub mod topic;
 
-pub use data_type::{DataType, VariableByte};
+pub use client_id::{validate_client_id, validate_empty_client_id};
+pub use data_type::{DataType, DataTypeKind, VariableByte};
+#[cfg(feature = "debug")]
+pub use debug::debug_bytes;
 pub use error::Error;
+pub use fixed_header::{decode_remaining_length, encode_remaining_length, frame, FixedHeader};
 pub use flags::{Flags, GenericFlags, PublishFlags};
 pub use packet_type::PacketType;
-pub use property::{Identifier, Property};
+pub use property::{Identifier, Property, PropertyBuilder};
+pub use protocol_version::ProtocolVersion;
+pub use qos::Qos;
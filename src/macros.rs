@@ -1,6 +1,8 @@
 /// Generates a public enum with the following traits implemented:
 /// * `From<T> for u8`
 /// * `TryFrom<u8> for T`
+/// * `Display for T`, printing the variant name
+/// * `FromStr for T`, parsing the variant name case-insensitively
 #[macro_export]
 macro_rules! build_enum {
   ($name:ident {
@@ -21,15 +23,35 @@ macro_rules! build_enum {
     }
 
     impl TryFrom<u8> for $name {
-        type Error = crate::Error;
-        fn try_from(v: u8) -> Result<Self, crate::Error> {
+        type Error = $crate::Error;
+        fn try_from(v: u8) -> Result<Self, $crate::Error> {
             return match v {
               $($value => Ok($name::$key),)*
-              _ => Err(crate::Error::ParseError)
+              _ => Err($crate::Error::ParseError)
             };
         }
     }
 
+    impl ::std::fmt::Display for $name {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            match self {
+                $($name::$key => f.write_str(stringify!($key))),*
+            }
+        }
+    }
+
+    impl ::std::str::FromStr for $name {
+        type Err = $crate::Error;
+        fn from_str(s: &str) -> Result<Self, $crate::Error> {
+            $(
+              if s.eq_ignore_ascii_case(stringify!($key)) {
+                return Ok($name::$key);
+              }
+            )*
+            Err($crate::Error::ParseError)
+        }
+    }
+
     #[cfg(test)]
     mod enum_tests {
       use super::$name;
@@ -41,8 +63,17 @@ macro_rules! build_enum {
           let id = $name::$key;
           assert_eq!(u8::from(id), $value);
           assert_eq!($name::try_from($value).unwrap(), id);
+          assert_eq!(id.to_string(), stringify!($key));
+          assert_eq!(stringify!($key).parse::<$name>().unwrap(), id);
+          assert_eq!(stringify!($key).to_lowercase().parse::<$name>().unwrap(), id);
         }
       )*
+
+      #[test]
+      fn from_str_error() {
+        assert_eq!("".parse::<$name>().unwrap_err(), $crate::Error::ParseError);
+        assert_eq!("unknown".parse::<$name>().unwrap_err(), $crate::Error::ParseError);
+      }
     }
   };
 }
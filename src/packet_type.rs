@@ -66,6 +66,61 @@ impl PacketType {
       Err(Error::ParseError)
     }
   }
+
+  /// Build the first byte of the fixed header as a DataType::Byte, with the
+  /// packet type in bits 7-4 and `flags` in bits 3-0. Only the low four bits
+  /// of `flags` are used. Use `Flags::to_header_byte` to have the reserved
+  /// flag values checked.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{DataType, PacketType};
+  ///
+  /// assert_eq!(PacketType::SUBSCRIBE.to_header_data_type(0x02), DataType::Byte(0x82));
+  /// ```
+  pub fn to_header_data_type(self, flags: u8) -> DataType {
+    DataType::Byte((u8::from(self) << 4) | (flags & 0x0F))
+  }
+
+  /// Returns true if the variable header of this packet type contains a
+  /// Packet Identifier.
+  ///
+  /// [2.2.1 Packet Identifier](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901026)
+  ///
+  /// A PUBLISH packet only contains a Packet Identifier when its QoS is 1 or
+  /// 2, so callers need to check the PUBLISH flags as well.
+  pub fn carries_packet_identifier(self) -> bool {
+    use PacketType::*;
+
+    matches!(
+      self,
+      PUBLISH | PUBACK | PUBREC | PUBREL | PUBCOMP | SUBSCRIBE | SUBACK | UNSUBSCRIBE | UNSUBACK
+    )
+  }
+
+  /// Returns true if this packet type may have a payload.
+  ///
+  /// [2.3 Payload](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901030)
+  ///
+  /// A payload is always present in CONNECT, SUBSCRIBE, SUBACK, UNSUBSCRIBE
+  /// and UNSUBACK, and optional in PUBLISH, so a PUBLISH payload may still be
+  /// empty. All other packet types, including DISCONNECT and AUTH, never have
+  /// a payload.
+  pub fn has_payload(self) -> bool {
+    use PacketType::*;
+
+    matches!(
+      self,
+      CONNECT | PUBLISH | SUBSCRIBE | SUBACK | UNSUBSCRIBE | UNSUBACK
+    )
+  }
+
+  /// Returns true if the variable header of this packet type contains a
+  /// property block. PINGREQ and PINGRESP are the only packet types without one.
+  pub fn carries_properties(self) -> bool {
+    !matches!(self, PacketType::PINGREQ | PacketType::PINGRESP)
+  }
 }
 
 #[cfg(test)]
@@ -105,4 +160,45 @@ mod tests {
     let err = super::PacketType::new(&mut err_reader).unwrap_err();
     assert_eq!(err, crate::Error::ParseError)
   }
+
+  #[test]
+  fn carries_packet_identifier() {
+    assert!(super::PacketType::PUBLISH.carries_packet_identifier());
+    assert!(super::PacketType::SUBACK.carries_packet_identifier());
+    assert!(!super::PacketType::CONNECT.carries_packet_identifier());
+    assert!(!super::PacketType::PINGREQ.carries_packet_identifier());
+  }
+
+  #[test]
+  fn carries_properties() {
+    assert!(super::PacketType::CONNECT.carries_properties());
+    assert!(super::PacketType::PUBACK.carries_properties());
+    assert!(!super::PacketType::PINGREQ.carries_properties());
+    assert!(!super::PacketType::PINGRESP.carries_properties());
+  }
+
+  #[test]
+  fn to_header_data_type() {
+    let header = super::PacketType::CONNECT.to_header_data_type(0);
+    assert_eq!(header, crate::DataType::Byte(0x10));
+
+    let bytes = header.to_vec().unwrap();
+    let mut reader = io::BufReader::new(&bytes[..]);
+    let packet_type = super::PacketType::new(&mut reader);
+    assert_eq!(packet_type.unwrap(), super::PacketType::CONNECT);
+
+    let header = super::PacketType::PUBLISH.to_header_data_type(0xFD);
+    assert_eq!(header, crate::DataType::Byte(0x3D));
+  }
+
+  #[test]
+  fn has_payload() {
+    assert!(super::PacketType::CONNECT.has_payload());
+    assert!(super::PacketType::PUBLISH.has_payload());
+    assert!(super::PacketType::UNSUBACK.has_payload());
+    assert!(!super::PacketType::CONNACK.has_payload());
+    assert!(!super::PacketType::PINGREQ.has_payload());
+    assert!(!super::PacketType::DISCONNECT.has_payload());
+    assert!(!super::PacketType::AUTH.has_payload());
+  }
 }
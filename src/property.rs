@@ -1,9 +1,11 @@
 use crate::build_enum;
-use crate::DataType;
+use crate::storage::Values;
 use crate::Error;
-use std::collections::BTreeMap;
+use crate::{DataType, DataTypeKind, VariableByte};
+use crate::{PacketType, Qos};
 use std::convert::TryFrom;
 use std::io;
+use std::iter::FromIterator;
 
 build_enum!(Identifier {
   PayloadFormatIndicator = 0x01,
@@ -35,6 +37,117 @@ build_enum!(Identifier {
   SharedSubscriptionAvailable = 0x2a
 });
 
+impl Identifier {
+  /// Returns true if the property may appear more than once in a property
+  /// block. User Property may be repeated in any packet that allows it, and
+  /// Subscription Identifier may be repeated in a PUBLISH sent by the Server.
+  pub fn is_repeatable(self) -> bool {
+    self == Identifier::UserProperty || self == Identifier::SubscriptionIdentifier
+  }
+
+  /// The data type of the property's value.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{DataTypeKind, Identifier};
+  ///
+  /// assert_eq!(Identifier::TopicAlias.expected_type(), DataTypeKind::TwoByteInteger);
+  /// assert_eq!(Identifier::UserProperty.expected_type(), DataTypeKind::Utf8StringPair);
+  /// ```
+  pub fn expected_type(self) -> DataTypeKind {
+    use Identifier::*;
+
+    match self {
+      PayloadFormatIndicator
+      | RequestProblemInformation
+      | RequestResponseInformation
+      | MaximumQos
+      | RetainAvailable
+      | WildcardSubscriptionAvailable
+      | SubscriptionIdentifierAvailable
+      | SharedSubscriptionAvailable => DataTypeKind::Byte,
+      ServerKeepAlive | ReceiveMaximum | TopicAliasMaximum | TopicAlias => {
+        DataTypeKind::TwoByteInteger
+      }
+      MessageExpiryInterval | SessionExpiryInterval | WillDelayInterval | MaximumPacketSize => {
+        DataTypeKind::FourByteInteger
+      }
+      SubscriptionIdentifier => DataTypeKind::VariableByteInteger,
+      UserProperty => DataTypeKind::Utf8StringPair,
+      CorrelationData | AuthenticationData => DataTypeKind::BinaryData,
+      ContentType
+      | ResponseTopic
+      | AssignedClientIdentifier
+      | AuthenticationMethod
+      | ResponseInformation
+      | ServerReference
+      | ReasonString => DataTypeKind::Utf8EncodedString,
+    }
+  }
+
+  /// Returns true if the property is allowed in the Will Properties of a
+  /// CONNECT payload.
+  ///
+  /// [3.1.3.2 Will Properties](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901060)
+  pub fn is_valid_for_will(self) -> bool {
+    use Identifier::*;
+
+    matches!(
+      self,
+      WillDelayInterval
+        | PayloadFormatIndicator
+        | MessageExpiryInterval
+        | ContentType
+        | ResponseTopic
+        | CorrelationData
+        | UserProperty
+    )
+  }
+
+  /// Returns true if the property is allowed in the variable header of the
+  /// given packet type.
+  ///
+  /// [2.2.2.2 Property](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901027)
+  pub fn is_valid_for(self, packet_type: PacketType) -> bool {
+    use Identifier::*;
+    use PacketType::*;
+
+    match self {
+      PayloadFormatIndicator
+      | MessageExpiryInterval
+      | ContentType
+      | ResponseTopic
+      | CorrelationData
+      | TopicAlias => packet_type == PUBLISH,
+      SubscriptionIdentifier => matches!(packet_type, PUBLISH | SUBSCRIBE),
+      SessionExpiryInterval => matches!(packet_type, CONNECT | CONNACK | DISCONNECT),
+      AuthenticationMethod | AuthenticationData => matches!(packet_type, CONNECT | CONNACK | AUTH),
+      RequestProblemInformation | RequestResponseInformation => packet_type == CONNECT,
+      ReceiveMaximum | TopicAliasMaximum | MaximumPacketSize => {
+        matches!(packet_type, CONNECT | CONNACK)
+      }
+      AssignedClientIdentifier
+      | ServerKeepAlive
+      | ResponseInformation
+      | MaximumQos
+      | RetainAvailable
+      | WildcardSubscriptionAvailable
+      | SubscriptionIdentifierAvailable
+      | SharedSubscriptionAvailable => packet_type == CONNACK,
+      ServerReference => matches!(packet_type, CONNACK | DISCONNECT),
+      // Will Delay Interval is only valid in the Will Properties of a CONNECT
+      // payload, see is_valid_for_will
+      WillDelayInterval => false,
+      ReasonString => matches!(
+        packet_type,
+        CONNACK | PUBACK | PUBREC | PUBREL | PUBCOMP | SUBACK | UNSUBACK | DISCONNECT | AUTH
+      ),
+      UserProperty => !matches!(packet_type, PINGREQ | PINGRESP),
+    }
+  }
+}
+
 /// A Property consists of an Identifier which defines its usage and data type,
 /// followed by a value.
 ///
@@ -47,97 +160,1097 @@ build_enum!(Identifier {
 /// Malformed Packet. If received, use a CONNACK or DISCONNECT packet with
 /// Reason Code 0x81 (Malformed Packet). There is no significance in the order
 /// of Properties with different Identifiers.
+///
+/// Values are stored in a list per Identifier, because User Property and
+/// Subscription Identifier may appear more than once. Every other Identifier
+/// holds at most one value. Use `get`, `get_all`, `insert`, `push` and
+/// `remove` to access them.
+#[derive(Debug, Default, PartialEq)]
 pub struct Property {
-  pub values: BTreeMap<Identifier, DataType>,
+  values: Values,
 }
 
 impl Property {
-  /// Parse property identifiers and values from a reader.
+  /// Create a `PropertyBuilder` for generating properties.
+  pub fn builder() -> PropertyBuilder {
+    PropertyBuilder::default()
+  }
+
+  /// Parse property identifiers and values from a reader. The property block
+  /// starts with its length encoded as a Variable Byte Integer, and a length
+  /// of 0 means there are no properties.
+  ///
+  /// It is a Malformed Packet if an Identifier other than User Property or
+  /// Subscription Identifier appears more than once, or if an Identifier is
+  /// reserved, and a Protocol Error if a Subscription Identifier has a value
+  /// of 0.
+  ///
+  /// Values are read a few bytes at a time, so wrap unbuffered readers such as
+  /// a `TcpStream` in an `io::BufReader`. Use `from_slice` when the bytes are
+  /// already in memory.
   pub fn new<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
-    let mut length: u16 = DataType::parse_two_byte_int(reader)?.into();
-    let mut properties = BTreeMap::new();
+    Ok(Self::parse_counted(reader)?.0)
+  }
+
+  /// Same as `parse_counted`, but reads directly from a slice instead of going
+  /// through a reader. Returns `Error::Incomplete` if the slice ends before
+  /// the Property Length or the block it declares.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  ///
+  /// let data: Vec<u8> = vec![0x02, 0x24, 0x01, 0xAA, 0xBB];
+  /// let (property, consumed) = Property::from_slice(&data).unwrap();
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), Some(1));
+  /// assert_eq!(consumed, 3);
+  /// ```
+  pub fn from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    let (length, prefix) = match DataType::parse_variable_byte_int_from_slice(bytes)? {
+      (DataType::VariableByteInteger(length), prefix) => (u32::from(&length), prefix),
+      _ => return Err(Error::ParseError),
+    };
+    let end = usize::try_from(length).map_err(|_| Error::MalformedPacket)? + prefix;
+
+    if bytes.len() < end {
+      return Err(Error::Incomplete {
+        needed: Some(end - bytes.len()),
+      });
+    }
+
+    let property = Self::parse_block(&bytes[prefix..end])?;
+    Ok((property, end))
+  }
+
+  /// Parse the properties of a block whose Property Length prefix has already
+  /// been read, so `block` holds exactly the identifiers and values. Returns
+  /// `Error::MalformedPacket` if the last property in `block` is cut short,
+  /// since the block is known to be complete.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  ///
+  /// let block: Vec<u8> = vec![0x24, 0x01, 0x23, 0x00, 0x0A];
+  /// let property = Property::from_block(&block).unwrap();
+  ///
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), Some(1));
+  /// assert_eq!(property.get_u16(Identifier::TopicAlias), Some(10));
+  /// ```
+  pub fn from_block(block: &[u8]) -> Result<Self, Error> {
+    Self::parse_block(block)
+  }
+
+  /// Parse a property block that is already complete in memory. Running out
+  /// of bytes inside it means the block is malformed, not that more data is
+  /// needed, so `Error::Incomplete` becomes `Error::MalformedPacket`.
+  fn parse_block(block: &[u8]) -> Result<Self, Error> {
+    let length = u32::try_from(block.len()).map_err(|_| Error::MalformedPacket)?;
+    let mut reader = block;
+
+    match Self::parse_entries(&mut reader, length, false) {
+      Err(Error::Incomplete { .. }) => Err(Error::MalformedPacket),
+      result => result,
+    }
+  }
+
+  /// Same as `new`, but also returns the number of bytes the property block
+  /// occupied, including the length prefix.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::Property;
+  /// use std::io;
+  ///
+  /// // a property block followed by two bytes of payload
+  /// let data: Vec<u8> = vec![0x02, 0x24, 0x01, 0xAA, 0xBB];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let (_property, consumed) = Property::parse_counted(&mut reader).unwrap();
+  /// assert_eq!(consumed, 3);
+  /// ```
+  pub fn parse_counted<R: io::Read>(reader: &mut R) -> Result<(Self, usize), Error> {
+    let mut reader = CountingReader {
+      inner: reader,
+      count: 0,
+    };
+    let property = Self::parse(&mut reader, false)?;
+    Ok((property, reader.count))
+  }
+
+  /// Same as `new`, but an unknown property identifier does not fail the
+  /// parse. Meant for tolerant tools such as logging proxies that may see
+  /// properties added by a newer version of the spec.
+  ///
+  /// The length of an unknown property's value can't be determined from the
+  /// identifier alone, so everything from the unknown identifier to the end
+  /// of the property block is skipped, including any known properties that
+  /// follow it. Only the properties before it are returned. If the reader
+  /// ends before the declared property length, `Error::Incomplete` is
+  /// returned.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  /// use std::io;
+  ///
+  /// // Maximum QoS followed by an unknown identifier 0x7F and its value
+  /// let data: Vec<u8> = vec![0x05, 0x24, 0x01, 0x7F, 0xAA, 0xBB];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let property = Property::new_lenient(&mut reader).unwrap();
+  ///
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), Some(1));
+  /// assert_eq!(property.len(), 1);
+  /// ```
+  pub fn new_lenient<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
+    Self::parse(reader, true)
+  }
+
+  fn parse<R: io::Read>(reader: &mut R, lenient: bool) -> Result<Self, Error> {
+    // the property length is encoded as a Variable Byte Integer
+    let length = match DataType::parse_variable_byte_int(reader)? {
+      DataType::VariableByteInteger(value) => u32::from(&value),
+      _ => return Err(Error::ParseError),
+    };
+
+    Self::parse_entries(reader, length, lenient)
+  }
+
+  /// Parse identifiers and values until `length` bytes have been read.
+  fn parse_entries<R: io::Read>(
+    reader: &mut R,
+    mut length: u32,
+    lenient: bool,
+  ) -> Result<Self, Error> {
+    let mut property = Self {
+      values: Values::default(),
+    };
 
     while length > 0 {
-      let identifier = Self::parse_identifier(reader)?;
+      let mut id_buffer = [0; 1];
+      reader.read_exact(&mut id_buffer)?;
       length -= 1;
 
+      let identifier = match Identifier::try_from(id_buffer[0]) {
+        Ok(identifier) => identifier,
+        Err(_) if lenient => {
+          Self::skip(reader, length)?;
+          break;
+        }
+        // reserved or unknown identifiers are a Malformed Packet
+        Err(_) => return Err(Error::MalformedPacket),
+      };
+
+      if !identifier.is_repeatable() && property.values.contains_key(identifier) {
+        return Err(Error::MalformedPacket);
+      }
+
       let data_type = Self::parse_type(identifier, reader)?;
-      let data_length = data_type.byte_len()?;
+      let data_length = data_type.encoded_len()?;
+
+      // It is a Protocol Error if the Subscription Identifier has a value of 0.
+      // The upper bound of 268,435,455 is enforced by the Variable Byte
+      // Integer decoder.
+      if identifier == Identifier::SubscriptionIdentifier
+        && data_type == DataType::VariableByteInteger(VariableByte::One(0))
+      {
+        return Err(Error::ProtocolError);
+      }
 
       // something is wrong if the total length of properties doesn't match
       if data_length > length {
         return Err(Error::MalformedPacket);
       } else {
-        length -= data_type.byte_len()?;
+        length -= data_length;
+      }
+
+      property.push(identifier, data_type);
+    }
+
+    Ok(property)
+  }
+
+  /// Check that every property is allowed in the given packet type and has a
+  /// value of the expected data type, that Subscription Identifier is only
+  /// repeated in a PUBLISH packet, and that each Subscription Identifier is in
+  /// the range 1 to 268,435,455. Returns `Error::MalformedPacket` otherwise.
+  ///
+  /// A Subscription Identifier or Topic Alias of 0, or a value other than 0 or
+  /// 1 for Maximum QoS or a property that holds a boolean, such as Payload
+  /// Format Indicator or Retain Available, returns `Error::ProtocolError`.
+  ///
+  /// [3.2.2.3.4 Maximum QoS](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901084)
+  pub fn validate(&self, packet_type: PacketType) -> Result<(), Error> {
+    for (identifier, values) in self.values.iter() {
+      if !identifier.is_valid_for(packet_type) {
+        return Err(Error::MalformedPacket);
+      }
+
+      if values.len() > 1
+        && identifier == Identifier::SubscriptionIdentifier
+        && packet_type != PacketType::PUBLISH
+      {
+        return Err(Error::MalformedPacket);
       }
 
-      properties.insert(identifier, data_type);
+      Self::validate_entry(identifier, values)?;
     }
 
-    Ok(Self { values: properties })
+    Ok(())
   }
 
-  /// Parse Identifier variant from reader.
-  fn parse_identifier<R: io::Read>(reader: &mut R) -> Result<Identifier, Error> {
-    let mut id_buffer = [0; 1];
-    reader.read_exact(&mut id_buffer)?;
-    Ok(Identifier::try_from(id_buffer[0])?)
+  /// Check the value constraints that hold in every packet type: each value
+  /// has the expected data type, only repeatable identifiers are repeated,
+  /// boolean properties and Maximum QoS are 0 or 1, Subscription Identifier
+  /// is in the range 1 to 268,435,455, and Topic Alias, Receive Maximum and
+  /// Maximum Packet Size are not 0. Unlike `validate`, this does not check
+  /// which identifiers are allowed in a given packet type.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, Property};
+  ///
+  /// let property = Property::builder().set_topic_alias(0).build();
+  /// assert_eq!(property.validate_values(), Err(Error::ProtocolError));
+  /// ```
+  pub fn validate_values(&self) -> Result<(), Error> {
+    for (identifier, values) in self.values.iter() {
+      Self::validate_entry(identifier, values)?;
+    }
+
+    if self.get_u16(Identifier::ReceiveMaximum) == Some(0)
+      || self.get_u32(Identifier::MaximumPacketSize) == Some(0)
+    {
+      return Err(Error::ProtocolError);
+    }
+
+    Ok(())
   }
 
-  /// Parse property values from a reader into DataType variants.
-  fn parse_type<R: io::Read>(identifier: Identifier, reader: &mut R) -> Result<DataType, Error> {
+  /// Check a Will Properties block from a CONNECT payload. Only the
+  /// identifiers allowed by `Identifier::is_valid_for_will` may appear, with
+  /// values of the expected data type. Returns `Error::MalformedPacket`
+  /// otherwise, or `Error::ProtocolError` if Payload Format Indicator is not
+  /// 0 or 1.
+  ///
+  /// [3.1.3.2 Will Properties](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901060)
+  pub fn validate_will(&self) -> Result<(), Error> {
+    for (identifier, values) in self.values.iter() {
+      if !identifier.is_valid_for_will() {
+        return Err(Error::MalformedPacket);
+      }
+
+      Self::validate_entry(identifier, values)?;
+    }
+
+    Ok(())
+  }
+
+  /// Checks shared by every property block: values must have the expected
+  /// data type, only repeatable identifiers may have more than one, byte
+  /// properties that hold a boolean, as well as Maximum QoS, must be 0 or 1,
+  /// Subscription Identifier must be in range and Topic Alias must not be 0.
+  fn validate_entry(identifier: Identifier, values: &[DataType]) -> Result<(), Error> {
     use Identifier::*;
 
-    match identifier {
-      PayloadFormatIndicator
-      | RequestProblemInformation
-      | RequestResponseInformation
-      | MaximumQos
-      | RetainAvailable
-      | WildcardSubscriptionAvailable
-      | SubscriptionIdentifierAvailable
-      | SharedSubscriptionAvailable => DataType::parse_byte(reader),
-      ServerKeepAlive | ReceiveMaximum | TopicAliasMaximum | TopicAlias => {
-        DataType::parse_two_byte_int(reader)
+    if values
+      .iter()
+      .any(|value| value.kind() != identifier.expected_type())
+    {
+      return Err(Error::MalformedPacket);
+    }
+
+    if values.len() > 1 && !identifier.is_repeatable() {
+      return Err(Error::MalformedPacket);
+    }
+
+    // Maximum QoS is absent rather than 2 when QoS 2 is supported
+    let zero_or_one = matches!(
+      identifier,
+      MaximumQos
+        | PayloadFormatIndicator
+        | RequestProblemInformation
+        | RequestResponseInformation
+        | RetainAvailable
+        | WildcardSubscriptionAvailable
+        | SubscriptionIdentifierAvailable
+        | SharedSubscriptionAvailable
+    );
+
+    if zero_or_one
+      && values
+        .iter()
+        .any(|value| !matches!(value, DataType::Byte(0) | DataType::Byte(1)))
+    {
+      return Err(Error::ProtocolError);
+    }
+
+    if identifier == SubscriptionIdentifier {
+      for value in values {
+        let id = match value {
+          DataType::VariableByteInteger(value) => u32::from(value),
+          _ => return Err(Error::MalformedPacket),
+        };
+
+        if id == 0 {
+          return Err(Error::ProtocolError);
+        } else if id > 268_435_455 {
+          return Err(Error::MalformedPacket);
+        }
       }
-      MessageExpiryInterval | SessionExpiryInterval | WillDelayInterval | MaximumPacketSize => {
-        DataType::parse_four_byte_int(reader)
+    }
+
+    if identifier == TopicAlias {
+      if let Some(DataType::TwoByteInteger(0)) = values.first() {
+        return Err(Error::ProtocolError);
       }
-      SubscriptionIdentifier => DataType::parse_variable_byte_int(reader),
-      UserProperty => DataType::parse_utf8_string_pair(reader),
-      CorrelationData | AuthenticationData => DataType::parse_binary_data(reader),
-      ContentType
-      | ResponseTopic
-      | AssignedClientIdentifier
-      | AuthenticationMethod
-      | ResponseInformation
-      | ServerReference
-      | ReasonString => DataType::parse_utf8_string(reader),
+    }
+
+    Ok(())
+  }
+
+  /// Check that Authentication Data is only present together with
+  /// Authentication Method, as required for CONNECT, CONNACK and AUTH.
+  /// Returns `Error::ProtocolError` otherwise.
+  ///
+  /// [3.1.2.11.10 Authentication Data](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901056)
+  pub fn validate_auth(&self) -> Result<(), Error> {
+    if self.values.contains_key(Identifier::AuthenticationData)
+      && !self.values.contains_key(Identifier::AuthenticationMethod)
+    {
+      return Err(Error::ProtocolError);
+    }
+
+    Ok(())
+  }
+
+  /// Check the properties of an AUTH packet with Reason Code 0x18 (Continue
+  /// authentication), as sent by either side during an enhanced
+  /// authentication exchange such as SCRAM (see 4.12 Enhanced
+  /// authentication). Runs `validate` for AUTH, then returns
+  /// `Error::ProtocolError` unless both Authentication Method and
+  /// Authentication Data are present.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, Property};
+  ///
+  /// let property = Property::builder()
+  ///   .set_authentication_method(String::from("SCRAM-SHA-256"))
+  ///   .build();
+  ///
+  /// assert_eq!(property.validate_continue_auth(), Err(Error::ProtocolError));
+  /// ```
+  pub fn validate_continue_auth(&self) -> Result<(), Error> {
+    self.validate(PacketType::AUTH)?;
+
+    if !self.values.contains_key(Identifier::AuthenticationMethod)
+      || !self.values.contains_key(Identifier::AuthenticationData)
+    {
+      return Err(Error::ProtocolError);
+    }
+
+    Ok(())
+  }
+
+  /// Check a PUBLISH Topic Alias against the Topic Alias Maximum the receiver
+  /// sent in CONNECT or CONNACK. Returns `Error::ProtocolError` if the alias is
+  /// 0 or greater than `maximum`. A missing Topic Alias passes.
+  ///
+  /// [3.3.2.3.4 Topic Alias](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901113)
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, Property};
+  ///
+  /// let property = Property::builder().set_topic_alias(11).build();
+  /// assert_eq!(property.validate_topic_alias(10), Err(Error::ProtocolError));
+  /// ```
+  pub fn validate_topic_alias(&self, maximum: u16) -> Result<(), Error> {
+    match self.get_u16(Identifier::TopicAlias) {
+      Some(alias) if alias == 0 || alias > maximum => Err(Error::ProtocolError),
+      _ => Ok(()),
+    }
+  }
+
+  /// Check the value constraints on CONNECT and CONNACK properties. Returns
+  /// `Error::MalformedPacket` if Receive Maximum or Maximum Packet Size holds
+  /// the wrong data type, and `Error::ProtocolError` if either is 0.
+  ///
+  /// [3.1.2.11.3 Receive Maximum](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901049)
+  ///
+  /// [3.1.2.11.4 Maximum Packet Size](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901050)
+  pub fn validate_connect_constraints(&self) -> Result<(), Error> {
+    for identifier in [Identifier::ReceiveMaximum, Identifier::MaximumPacketSize] {
+      if let Some(values) = self.values.get(identifier) {
+        Self::validate_entry(identifier, values)?;
+      }
+    }
+
+    if self.get_u16(Identifier::ReceiveMaximum) == Some(0) {
+      return Err(Error::ProtocolError);
+    }
+
+    if self.get_u32(Identifier::MaximumPacketSize) == Some(0) {
+      return Err(Error::ProtocolError);
+    }
+
+    Ok(())
+  }
+
+  /// Get the first value stored for an Identifier.
+  pub fn get(&self, identifier: Identifier) -> Option<&DataType> {
+    self
+      .values
+      .get(identifier)
+      .and_then(|values| values.first())
+  }
+
+  /// Set the value of an Identifier, replacing any existing values.
+  pub fn insert(&mut self, identifier: Identifier, value: DataType) {
+    self.values.insert(identifier, vec![value]);
+  }
+
+  /// Append a value to an Identifier. Use this for User Property and
+  /// Subscription Identifier, which may appear more than once.
+  pub fn push(&mut self, identifier: Identifier, value: DataType) {
+    self.values.push(identifier, value);
+  }
+
+  /// Get every value stored for an Identifier, in the order they were added.
+  /// Returns an empty slice if the Identifier is absent.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  ///
+  /// let mut property = Property::builder()
+  ///   .set_subscription_identifier(1)?
+  ///   .build();
+  /// property.merge(Property::builder().set_subscription_identifier(2)?.build());
+  ///
+  /// assert_eq!(property.get_all(Identifier::SubscriptionIdentifier).len(), 2);
+  /// assert!(property.get_all(Identifier::TopicAlias).is_empty());
+  /// # Ok::<(), mqtt_packet::Error>(())
+  /// ```
+  pub fn get_all(&self, identifier: Identifier) -> &[DataType] {
+    self.values.get(identifier).unwrap_or(&[])
+  }
+
+  /// Remove every value stored for an Identifier and return them.
+  pub fn remove(&mut self, identifier: Identifier) -> Vec<DataType> {
+    self.values.remove(identifier).unwrap_or_default()
+  }
+
+  /// The number of values, counting each repeated value separately.
+  pub fn len(&self) -> usize {
+    self.values.iter().map(|(_, values)| values.len()).sum()
+  }
+
+  /// Returns `true` if there are no values.
+  pub fn is_empty(&self) -> bool {
+    self.values.is_empty()
+  }
+
+  /// Merge the values of another Property into this one. User Property and
+  /// Subscription Identifier values are appended after the existing values;
+  /// any other identifier in `other` replaces the existing value.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  ///
+  /// let mut property = Property::builder()
+  ///   .set_subscription_identifier(1)?
+  ///   .build();
+  /// property.merge(Property::builder().set_subscription_identifier(2)?.build());
+  ///
+  /// assert_eq!(property.get_all(Identifier::SubscriptionIdentifier).len(), 2);
+  /// # Ok::<(), mqtt_packet::Error>(())
+  /// ```
+  pub fn merge(&mut self, other: Property) {
+    for (identifier, value) in other {
+      self.add(identifier, value);
+    }
+  }
+
+  /// Append repeatable identifiers and replace the rest.
+  fn add(&mut self, identifier: Identifier, value: DataType) {
+    if identifier.is_repeatable() {
+      self.push(identifier, value);
+    } else {
+      self.insert(identifier, value);
+    }
+  }
+
+  /// Get the value of a Byte property. Returns `None` if the property is
+  /// absent or is not a `DataType::Byte`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property};
+  /// use std::io;
+  ///
+  /// let data: Vec<u8> = vec![0x02, 0x24, 0x01];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let property = Property::new(&mut reader).unwrap();
+  ///
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), Some(1));
+  /// assert_eq!(property.get_u8(Identifier::RetainAvailable), None);
+  /// ```
+  pub fn get_u8(&self, identifier: Identifier) -> Option<u8> {
+    match self.get(identifier) {
+      Some(DataType::Byte(value)) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Get the value of a Two Byte Integer property. Returns `None` if the
+  /// property is absent or is not a `DataType::TwoByteInteger`.
+  pub fn get_u16(&self, identifier: Identifier) -> Option<u16> {
+    match self.get(identifier) {
+      Some(DataType::TwoByteInteger(value)) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Get the value of a Four Byte Integer or Variable Byte Integer property.
+  /// Returns `None` if the property is absent or is not one of those types.
+  pub fn get_u32(&self, identifier: Identifier) -> Option<u32> {
+    match self.get(identifier) {
+      Some(DataType::FourByteInteger(value)) => Some(*value),
+      Some(DataType::VariableByteInteger(value)) => Some(u32::from(value)),
+      _ => None,
+    }
+  }
+
+  /// Get the value of a UTF-8 Encoded String property. Returns `None` if the
+  /// property is absent or is not a `DataType::Utf8EncodedString`.
+  pub fn get_string(&self, identifier: Identifier) -> Option<&str> {
+    match self.get(identifier) {
+      Some(DataType::Utf8EncodedString(value)) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Get the value of a Binary Data property. Returns `None` if the property
+  /// is absent or is not a `DataType::BinaryData`.
+  pub fn get_binary(&self, identifier: Identifier) -> Option<&[u8]> {
+    match self.get(identifier) {
+      Some(DataType::BinaryData(value)) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Get the name-value pairs stored under `Identifier::UserProperty`, in the
+  /// order they appear on the wire.
+  pub fn get_user_properties(&self) -> Vec<(&str, &str)> {
+    match self.values.get(Identifier::UserProperty) {
+      Some(values) => values
+        .iter()
+        .filter_map(|value| match value {
+          DataType::Utf8StringPair(name, value) => Some((name.as_str(), value.as_str())),
+          _ => None,
+        })
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  /// Same as `get_user_properties`, but returns owned copies of the pairs,
+  /// e.g. for propagating them to another packet.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::Property;
+  ///
+  /// let property = Property::builder()
+  ///   .add_user_property(String::from("trace-id"), String::from("abc"))
+  ///   .build();
+  ///
+  /// assert_eq!(
+  ///   property.user_properties(),
+  ///   vec![(String::from("trace-id"), String::from("abc"))]
+  /// );
+  /// ```
+  pub fn user_properties(&self) -> Vec<(String, String)> {
+    self
+      .get_user_properties()
+      .into_iter()
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+      .collect()
+  }
+
+  /// Format each property value as `IdentifierName = Value`, one per line,
+  /// in the order `generate` writes them. Intended for logging, e.g. when a
+  /// packet is rejected.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::Property;
+  ///
+  /// let property = Property::builder()
+  ///   .set_session_expiry_interval(3600)
+  ///   .add_user_property(String::from("a"), String::from("1"))
+  ///   .add_user_property(String::from("b"), String::from("2"))
+  ///   .build();
+  ///
+  /// assert_eq!(
+  ///   property.to_debug_string(),
+  ///   "SessionExpiryInterval = 3600\nUserProperty = \"a\": \"1\"\nUserProperty = \"b\": \"2\""
+  /// );
+  /// ```
+  pub fn to_debug_string(&self) -> String {
+    self
+      .iter_wire_order()
+      .map(|(key, value)| format!("{} = {}", key, value))
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Read and discard the rest of a property block.
+  fn skip<R: io::Read>(reader: &mut R, length: u32) -> Result<(), Error> {
+    let skipped = io::copy(
+      &mut io::Read::take(reader, u64::from(length)),
+      &mut io::sink(),
+    )?;
+
+    if skipped != u64::from(length) {
+      return Err(Error::Incomplete {
+        needed: usize::try_from(u64::from(length) - skipped).ok(),
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Parse property values from a reader into DataType variants.
+  fn parse_type<R: io::Read>(identifier: Identifier, reader: &mut R) -> Result<DataType, Error> {
+    match identifier.expected_type() {
+      DataTypeKind::Byte => DataType::parse_byte(reader),
+      DataTypeKind::TwoByteInteger => DataType::parse_two_byte_int(reader),
+      DataTypeKind::FourByteInteger => DataType::parse_four_byte_int(reader),
+      DataTypeKind::VariableByteInteger => DataType::parse_variable_byte_int(reader),
+      DataTypeKind::Utf8EncodedString => DataType::parse_utf8_string(reader),
+      DataTypeKind::BinaryData => DataType::parse_binary_data(reader),
+      DataTypeKind::Utf8StringPair => DataType::parse_utf8_string_pair(reader),
     }
   }
 
-  /// Convert Property values into a byte vector.
+  /// Iterate over properties in the order `generate` writes them.
+  ///
+  /// Properties are ordered by their numeric Identifier value. Repeated
+  /// properties, such as User Property, are yielded in the order they were
+  /// inserted, which the spec requires to be preserved.
+  pub fn iter_wire_order(&self) -> impl Iterator<Item = (Identifier, &DataType)> + '_ {
+    // sort by the identifier value rather than relying on the order the enum
+    // variants are declared in
+    let mut entries: Vec<_> = self.values.iter().collect();
+    entries.sort_by_key(|(key, _)| u8::from(*key));
+
+    entries
+      .into_iter()
+      .flat_map(|(key, values)| values.iter().map(move |value| (key, value)))
+  }
+
+  /// The number of bytes `generate` writes, including the Variable Byte
+  /// Integer length prefix, without generating the property block.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::Property;
+  ///
+  /// let property = Property::builder().set_topic_alias(1).build();
+  /// assert_eq!(property.encoded_len().unwrap(), 4);
+  /// ```
+  pub fn encoded_len(&self) -> Result<u32, Error> {
+    let mut length: u32 = 0;
+
+    for (_key, value) in self.iter_wire_order() {
+      // one byte for the identifier followed by the value
+      length = length
+        .checked_add(1 + value.encoded_len()?)
+        .ok_or(Error::GenerateError)?;
+    }
+
+    let prefix = DataType::VariableByteInteger(VariableByte::from(length)).encoded_len()?;
+
+    Ok(prefix + length)
+  }
+
+  /// Convert Property values into a byte vector. See `iter_wire_order` for
+  /// the order properties are written in.
   pub fn generate(&self) -> Result<Vec<u8>, Error> {
     // create a vector to hold the generated data
     let mut props = vec![];
 
-    // PartialOrd sorts enum variants in the order they are declared.
-    for (key, value) in self.values.iter() {
-      let id: u8 = u8::from(*key);
-      props.push(vec![id]);
+    for (key, value) in self.iter_wire_order() {
+      props.push(vec![u8::from(key)]);
       props.push(value.to_vec()?);
     }
 
     let bytes = props.concat();
 
-    // we need to fit the usize into a u16, so we can grab the first two bytes
-    let length = u16::try_from(bytes.len() & 0xFFFF)
-      .unwrap()
-      .to_be_bytes()
-      .to_vec();
+    // the property length is encoded as a Variable Byte Integer
+    let length = u32::try_from(bytes.len()).map_err(|_| Error::GenerateError)?;
+    let length = DataType::VariableByteInteger(VariableByte::from(length)).to_vec()?;
 
-    let result = vec![length, bytes];
+    let result = [length, bytes];
 
     Ok(result.concat())
   }
 }
+
+/// Iterates over properties in wire order. See `Property::iter_wire_order`.
+impl<'a> IntoIterator for &'a Property {
+  type Item = (Identifier, &'a DataType);
+  type IntoIter = std::vec::IntoIter<Self::Item>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter_wire_order().collect::<Vec<_>>().into_iter()
+  }
+}
+
+/// Consumes the properties in wire order. See `Property::iter_wire_order`.
+impl IntoIterator for Property {
+  type Item = (Identifier, DataType);
+  type IntoIter = std::vec::IntoIter<Self::Item>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    let mut entries: Vec<_> = self.values.into_iter().collect();
+    entries.sort_by_key(|(key, _)| u8::from(*key));
+
+    entries
+      .into_iter()
+      .flat_map(|(key, values)| values.into_iter().map(move |value| (key, value)))
+      .collect::<Vec<_>>()
+      .into_iter()
+  }
+}
+
+/// Collects identifier-value pairs into a Property. Repeatable identifiers
+/// keep every value; for other identifiers the last value wins, the same as
+/// `Property::insert`.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::{DataType, Identifier, Property};
+///
+/// let property: Property = vec![
+///   (Identifier::TopicAlias, DataType::TwoByteInteger(1)),
+///   (Identifier::UserProperty, DataType::Utf8StringPair("a".into(), "1".into())),
+///   (Identifier::UserProperty, DataType::Utf8StringPair("b".into(), "2".into())),
+/// ]
+/// .into_iter()
+/// .collect();
+///
+/// assert_eq!(property.get_u16(Identifier::TopicAlias), Some(1));
+/// assert_eq!(property.get_user_properties(), vec![("a", "1"), ("b", "2")]);
+/// ```
+impl FromIterator<(Identifier, DataType)> for Property {
+  fn from_iter<I: IntoIterator<Item = (Identifier, DataType)>>(iter: I) -> Self {
+    let mut property = Self {
+      values: Values::default(),
+    };
+
+    for (identifier, value) in iter {
+      property.add(identifier, value);
+    }
+
+    property
+  }
+}
+
+/// Wraps a reader and counts the number of bytes read through it.
+struct CountingReader<'a, R> {
+  inner: &'a mut R,
+  count: usize,
+}
+
+impl<R: io::Read> io::Read for CountingReader<'_, R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.count += read;
+    Ok(read)
+  }
+}
+
+/// Builds a `Property` using typed setters, so that each Identifier is always
+/// paired with the data type defined for it by the spec.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::Property;
+///
+/// let property = Property::builder()
+///   .set_session_expiry_interval(3600)
+///   .set_content_type("text/plain".to_string())
+///   .build();
+///
+/// let expected: Vec<u8> = vec![
+///   0x12, 0x03, 0, 10, 116, 101, 120, 116, 47, 112, 108, 97, 105, 110, 0x11, 0x00, 0x00,
+///   0x0E, 0x10,
+/// ];
+/// assert_eq!(property.generate().unwrap(), expected);
+/// ```
+#[derive(Debug, Default)]
+pub struct PropertyBuilder {
+  values: Values,
+}
+
+impl PropertyBuilder {
+  fn set(mut self, identifier: Identifier, value: DataType) -> Self {
+    self.values.insert(identifier, vec![value]);
+    self
+  }
+
+  /// Set `Identifier::PayloadFormatIndicator`. `true` means the payload is
+  /// UTF-8 Encoded Character Data, `false` means unspecified bytes.
+  pub fn set_payload_format_indicator(self, value: bool) -> Self {
+    self.set(
+      Identifier::PayloadFormatIndicator,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Set `Identifier::MessageExpiryInterval`.
+  pub fn set_message_expiry_interval(self, value: u32) -> Self {
+    self.set(
+      Identifier::MessageExpiryInterval,
+      DataType::FourByteInteger(value),
+    )
+  }
+
+  /// Set `Identifier::ContentType`.
+  pub fn set_content_type(self, value: String) -> Self {
+    self.set(Identifier::ContentType, DataType::Utf8EncodedString(value))
+  }
+
+  /// Set `Identifier::ResponseTopic`.
+  pub fn set_response_topic(self, value: String) -> Self {
+    self.set(
+      Identifier::ResponseTopic,
+      DataType::Utf8EncodedString(value),
+    )
+  }
+
+  /// Set `Identifier::CorrelationData`.
+  pub fn set_correlation_data(self, value: Vec<u8>) -> Self {
+    self.set(Identifier::CorrelationData, DataType::BinaryData(value))
+  }
+
+  /// Set `Identifier::SubscriptionIdentifier`. Returns
+  /// `Error::GenerateError` if the value is 0 or larger than 268,435,455, the
+  /// range the spec allows.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Error, Identifier, Property};
+  ///
+  /// let property = Property::builder()
+  ///   .set_subscription_identifier(10)
+  ///   .unwrap()
+  ///   .build();
+  /// assert_eq!(property.get_u32(Identifier::SubscriptionIdentifier), Some(10));
+  ///
+  /// let err = Property::builder().set_subscription_identifier(0).unwrap_err();
+  /// assert_eq!(err, Error::GenerateError);
+  /// ```
+  pub fn set_subscription_identifier(self, value: u32) -> Result<Self, Error> {
+    if value == 0 {
+      return Err(Error::GenerateError);
+    }
+
+    let value = DataType::variable_byte(value)?;
+    Ok(self.set(Identifier::SubscriptionIdentifier, value))
+  }
+
+  /// Set `Identifier::SessionExpiryInterval`.
+  pub fn set_session_expiry_interval(self, value: u32) -> Self {
+    self.set(
+      Identifier::SessionExpiryInterval,
+      DataType::FourByteInteger(value),
+    )
+  }
+
+  /// Set `Identifier::AssignedClientIdentifier`.
+  pub fn set_assigned_client_identifier(self, value: String) -> Self {
+    self.set(
+      Identifier::AssignedClientIdentifier,
+      DataType::Utf8EncodedString(value),
+    )
+  }
+
+  /// Set `Identifier::ServerKeepAlive`.
+  pub fn set_server_keep_alive(self, value: u16) -> Self {
+    self.set(Identifier::ServerKeepAlive, DataType::TwoByteInteger(value))
+  }
+
+  /// Set `Identifier::AuthenticationMethod`.
+  pub fn set_authentication_method(self, value: String) -> Self {
+    self.set(
+      Identifier::AuthenticationMethod,
+      DataType::Utf8EncodedString(value),
+    )
+  }
+
+  /// Set `Identifier::AuthenticationData`.
+  pub fn set_authentication_data(self, value: Vec<u8>) -> Self {
+    self.set(Identifier::AuthenticationData, DataType::BinaryData(value))
+  }
+
+  /// Set `Identifier::RequestProblemInformation`.
+  pub fn set_request_problem_information(self, value: bool) -> Self {
+    self.set(
+      Identifier::RequestProblemInformation,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Set `Identifier::WillDelayInterval`.
+  pub fn set_will_delay_interval(self, value: u32) -> Self {
+    self.set(
+      Identifier::WillDelayInterval,
+      DataType::FourByteInteger(value),
+    )
+  }
+
+  /// Set `Identifier::RequestResponseInformation`.
+  pub fn set_request_response_information(self, value: bool) -> Self {
+    self.set(
+      Identifier::RequestResponseInformation,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Set `Identifier::ResponseInformation`.
+  pub fn set_response_information(self, value: String) -> Self {
+    self.set(
+      Identifier::ResponseInformation,
+      DataType::Utf8EncodedString(value),
+    )
+  }
+
+  /// Set `Identifier::ServerReference`.
+  pub fn set_server_reference(self, value: String) -> Self {
+    self.set(
+      Identifier::ServerReference,
+      DataType::Utf8EncodedString(value),
+    )
+  }
+
+  /// Set `Identifier::ReasonString`.
+  pub fn set_reason_string(self, value: String) -> Self {
+    self.set(Identifier::ReasonString, DataType::Utf8EncodedString(value))
+  }
+
+  /// Set `Identifier::ReceiveMaximum`.
+  pub fn set_receive_maximum(self, value: u16) -> Self {
+    self.set(Identifier::ReceiveMaximum, DataType::TwoByteInteger(value))
+  }
+
+  /// Set `Identifier::TopicAliasMaximum`.
+  pub fn set_topic_alias_maximum(self, value: u16) -> Self {
+    self.set(
+      Identifier::TopicAliasMaximum,
+      DataType::TwoByteInteger(value),
+    )
+  }
+
+  /// Set `Identifier::TopicAlias`.
+  pub fn set_topic_alias(self, value: u16) -> Self {
+    self.set(Identifier::TopicAlias, DataType::TwoByteInteger(value))
+  }
+
+  /// Set `Identifier::MaximumQos`. Maximum QoS may only hold 0 or 1, and
+  /// an absent value means QoS 2 is supported, so `Qos::ExactlyOnce` removes
+  /// the property instead of setting it.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::{Identifier, Property, Qos};
+  ///
+  /// let property = Property::builder().set_maximum_qos(Qos::AtLeastOnce).build();
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), Some(1));
+  ///
+  /// let property = Property::builder().set_maximum_qos(Qos::ExactlyOnce).build();
+  /// assert_eq!(property.get_u8(Identifier::MaximumQos), None);
+  /// ```
+  pub fn set_maximum_qos(mut self, value: Qos) -> Self {
+    if value == Qos::ExactlyOnce {
+      self.values.remove(Identifier::MaximumQos);
+      return self;
+    }
+
+    self.set(Identifier::MaximumQos, DataType::Byte(u8::from(value)))
+  }
+
+  /// Add a `Identifier::UserProperty` name-value pair.
+  pub fn add_user_property(mut self, name: String, value: String) -> Self {
+    self.values.push(
+      Identifier::UserProperty,
+      DataType::Utf8StringPair(name, value),
+    );
+    self
+  }
+
+  /// Set `Identifier::RetainAvailable`.
+  pub fn set_retain_available(self, value: bool) -> Self {
+    self.set(Identifier::RetainAvailable, DataType::Byte(u8::from(value)))
+  }
+
+  /// Set `Identifier::MaximumPacketSize`.
+  pub fn set_maximum_packet_size(self, value: u32) -> Self {
+    self.set(
+      Identifier::MaximumPacketSize,
+      DataType::FourByteInteger(value),
+    )
+  }
+
+  /// Set `Identifier::WildcardSubscriptionAvailable`.
+  pub fn set_wildcard_subscription_available(self, value: bool) -> Self {
+    self.set(
+      Identifier::WildcardSubscriptionAvailable,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Set `Identifier::SubscriptionIdentifierAvailable`.
+  pub fn set_subscription_identifier_available(self, value: bool) -> Self {
+    self.set(
+      Identifier::SubscriptionIdentifierAvailable,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Set `Identifier::SharedSubscriptionAvailable`.
+  pub fn set_shared_subscription_available(self, value: bool) -> Self {
+    self.set(
+      Identifier::SharedSubscriptionAvailable,
+      DataType::Byte(u8::from(value)),
+    )
+  }
+
+  /// Create the `Property` from the values that have been set.
+  pub fn build(self) -> Property {
+    Property {
+      values: self.values,
+    }
+  }
+}
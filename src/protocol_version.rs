@@ -0,0 +1,86 @@
+use crate::build_enum;
+use crate::Error;
+use std::convert::TryFrom;
+use std::io;
+
+// [3.1.2.2 Protocol Version](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901037)
+//
+// The one byte unsigned value that represents the revision level of the
+// protocol used by the Client. The value of the Protocol Version field for
+// version 5.0 of the protocol is 5 (0x05).
+build_enum!(
+  ProtocolVersion {
+    V3_1 = 3,
+    V3_1_1 = 4,
+    V5 = 5
+  }
+);
+
+impl ProtocolVersion {
+  /// Parse the Protocol Version byte of a CONNECT variable header. Returns
+  /// `Error::UnsupportedProtocolVersion` if the byte is not a known version.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use mqtt_packet::ProtocolVersion;
+  /// use std::io;
+  ///
+  /// let data: Vec<u8> = vec![0x04];
+  /// let mut reader = io::BufReader::new(&data[..]);
+  /// let version = ProtocolVersion::new(&mut reader).unwrap();
+  ///
+  /// assert_eq!(version, ProtocolVersion::V3_1_1);
+  /// assert!(version.check_supported().is_err());
+  /// ```
+  pub fn new<R: io::Read>(reader: &mut R) -> Result<Self, Error> {
+    let mut buffer = [0; 1];
+    reader.read_exact(&mut buffer)?;
+    Self::try_from(buffer[0]).map_err(|_| Error::UnsupportedProtocolVersion)
+  }
+
+  /// Returns `Error::UnsupportedProtocolVersion` for any version other than
+  /// 5, so a Server can reject the CONNECT with a CONNACK instead of treating
+  /// it as a Malformed Packet.
+  pub fn check_supported(self) -> Result<(), Error> {
+    match self {
+      ProtocolVersion::V5 => Ok(()),
+      _ => Err(Error::UnsupportedProtocolVersion),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ProtocolVersion;
+  use crate::Error;
+  use std::io;
+
+  fn parse(byte: u8) -> Result<ProtocolVersion, Error> {
+    let data: Vec<u8> = vec![byte];
+    let mut reader = io::BufReader::new(&data[..]);
+    ProtocolVersion::new(&mut reader)
+  }
+
+  #[test]
+  fn v5() {
+    let version = parse(5).unwrap();
+    assert_eq!(version, ProtocolVersion::V5);
+    assert_eq!(version.check_supported(), Ok(()));
+  }
+
+  #[test]
+  fn v3_1_1() {
+    let version = parse(4).unwrap();
+    assert_eq!(version, ProtocolVersion::V3_1_1);
+    assert_eq!(
+      version.check_supported(),
+      Err(Error::UnsupportedProtocolVersion)
+    );
+  }
+
+  #[test]
+  fn unknown() {
+    assert_eq!(parse(6).unwrap_err(), Error::UnsupportedProtocolVersion);
+  }
+}
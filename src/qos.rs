@@ -0,0 +1,14 @@
+use crate::build_enum;
+use std::convert::TryFrom;
+
+// [4.3 Quality of Service levels and protocol flows](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901234)
+//
+// The level of assurance for delivery of an Application Message. Position in
+// a PUBLISH fixed header: byte 1, bits 2-1.
+build_enum!(
+  Qos {
+    AtMostOnce = 0,
+    AtLeastOnce = 1,
+    ExactlyOnce = 2
+  }
+);
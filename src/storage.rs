@@ -0,0 +1,176 @@
+//! Storage for the values of a `Property`, kept sorted by Identifier.
+//!
+//! By default values are kept in a `BTreeMap`. With the `vec-storage` feature
+//! they are kept in a sorted `Vec` instead, which avoids the per-node
+//! allocations of a tree map. Property blocks only hold a handful of
+//! identifiers, so the linear layout is cheap to search and friendlier to
+//! targets where heap fragmentation is a concern. Both behave the same.
+
+use crate::{DataType, Identifier};
+use std::fmt;
+
+#[cfg(not(feature = "vec-storage"))]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "vec-storage"))]
+#[derive(Default, PartialEq)]
+pub(crate) struct Values(BTreeMap<Identifier, Vec<DataType>>);
+
+#[cfg(feature = "vec-storage")]
+#[derive(Default, PartialEq)]
+pub(crate) struct Values(Vec<(Identifier, Vec<DataType>)>);
+
+#[cfg(not(feature = "vec-storage"))]
+impl Values {
+  pub(crate) fn get(&self, identifier: Identifier) -> Option<&[DataType]> {
+    self.0.get(&identifier).map(Vec::as_slice)
+  }
+
+  pub(crate) fn insert(&mut self, identifier: Identifier, values: Vec<DataType>) {
+    self.0.insert(identifier, values);
+  }
+
+  pub(crate) fn push(&mut self, identifier: Identifier, value: DataType) {
+    self.0.entry(identifier).or_default().push(value);
+  }
+
+  pub(crate) fn remove(&mut self, identifier: Identifier) -> Option<Vec<DataType>> {
+    self.0.remove(&identifier)
+  }
+
+  pub(crate) fn iter(&self) -> impl Iterator<Item = (Identifier, &[DataType])> {
+    self.0.iter().map(|(key, values)| (*key, values.as_slice()))
+  }
+}
+
+#[cfg(not(feature = "vec-storage"))]
+impl IntoIterator for Values {
+  type Item = (Identifier, Vec<DataType>);
+  type IntoIter = std::collections::btree_map::IntoIter<Identifier, Vec<DataType>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.0.into_iter()
+  }
+}
+
+#[cfg(feature = "vec-storage")]
+impl Values {
+  /// Index of the identifier, or where it would be inserted to keep the
+  /// entries sorted.
+  fn position(&self, identifier: Identifier) -> Result<usize, usize> {
+    self.0.binary_search_by_key(&identifier, |(key, _)| *key)
+  }
+
+  pub(crate) fn get(&self, identifier: Identifier) -> Option<&[DataType]> {
+    match self.position(identifier) {
+      Ok(index) => Some(self.0[index].1.as_slice()),
+      Err(_) => None,
+    }
+  }
+
+  pub(crate) fn insert(&mut self, identifier: Identifier, values: Vec<DataType>) {
+    match self.position(identifier) {
+      Ok(index) => self.0[index].1 = values,
+      Err(index) => self.0.insert(index, (identifier, values)),
+    }
+  }
+
+  pub(crate) fn push(&mut self, identifier: Identifier, value: DataType) {
+    match self.position(identifier) {
+      Ok(index) => self.0[index].1.push(value),
+      Err(index) => self.0.insert(index, (identifier, vec![value])),
+    }
+  }
+
+  pub(crate) fn remove(&mut self, identifier: Identifier) -> Option<Vec<DataType>> {
+    match self.position(identifier) {
+      Ok(index) => Some(self.0.remove(index).1),
+      Err(_) => None,
+    }
+  }
+
+  pub(crate) fn iter(&self) -> impl Iterator<Item = (Identifier, &[DataType])> {
+    self.0.iter().map(|(key, values)| (*key, values.as_slice()))
+  }
+}
+
+#[cfg(feature = "vec-storage")]
+impl IntoIterator for Values {
+  type Item = (Identifier, Vec<DataType>);
+  type IntoIter = std::vec::IntoIter<Self::Item>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.0.into_iter()
+  }
+}
+
+impl Values {
+  pub(crate) fn contains_key(&self, identifier: Identifier) -> bool {
+    self.get(identifier).is_some()
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.iter().next().is_none()
+  }
+}
+
+/// Formats the same way for either backend, as a map of Identifier to values.
+impl fmt::Debug for Values {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_map().entries(self.iter()).finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Values;
+  use crate::{DataType, Identifier};
+
+  #[test]
+  fn sorted() {
+    let mut values = Values::default();
+    values.push(Identifier::UserProperty, DataType::Byte(1));
+    values.insert(Identifier::TopicAlias, vec![DataType::TwoByteInteger(1)]);
+    values.push(Identifier::PayloadFormatIndicator, DataType::Byte(0));
+    values.push(Identifier::UserProperty, DataType::Byte(2));
+
+    let keys: Vec<_> = values.iter().map(|(key, _)| key).collect();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+
+    assert_eq!(
+      values.get(Identifier::UserProperty),
+      Some(&[DataType::Byte(1), DataType::Byte(2)][..])
+    );
+  }
+
+  #[test]
+  fn insert_remove() {
+    let mut values = Values::default();
+    assert!(values.is_empty());
+
+    values.push(Identifier::TopicAlias, DataType::TwoByteInteger(1));
+    values.insert(Identifier::TopicAlias, vec![DataType::TwoByteInteger(2)]);
+    assert_eq!(
+      values.get(Identifier::TopicAlias),
+      Some(&[DataType::TwoByteInteger(2)][..])
+    );
+    assert!(values.contains_key(Identifier::TopicAlias));
+
+    assert_eq!(
+      values.remove(Identifier::TopicAlias),
+      Some(vec![DataType::TwoByteInteger(2)])
+    );
+    assert_eq!(values.remove(Identifier::TopicAlias), None);
+    assert!(!values.contains_key(Identifier::TopicAlias));
+    assert!(values.is_empty());
+  }
+
+  #[test]
+  fn debug() {
+    let mut values = Values::default();
+    values.push(Identifier::TopicAlias, DataType::TwoByteInteger(1));
+    assert_eq!(format!("{:?}", values), "{TopicAlias: [TwoByteInteger(1)]}");
+  }
+}
@@ -0,0 +1,55 @@
+//! Helpers for working with Topic Names and Topic Filters.
+//!
+//! [4.7 Topic Names and Topic Filters](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901241)
+
+/// Split a Topic Name or Topic Filter into its topic levels.
+///
+/// [4.7.1.1 Topic level separator](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901243)
+///
+/// The forward slash (‘/’ U+002F) is used to separate each level within a
+/// topic tree and provide a hierarchical structure to the Topic Names. Adjacent
+/// Topic level separators indicate a zero-length topic level, so empty levels
+/// are yielded as empty strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use mqtt_packet::topic;
+///
+/// let levels: Vec<&str> = topic::levels("sport/tennis//player1").collect();
+/// assert_eq!(levels, vec!["sport", "tennis", "", "player1"]);
+/// ```
+pub fn levels(topic: &str) -> impl Iterator<Item = &str> {
+  topic.split('/')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::levels;
+
+  fn collect(topic: &str) -> Vec<&str> {
+    levels(topic).collect()
+  }
+
+  #[test]
+  fn empty_level() {
+    assert_eq!(collect("a//b"), vec!["a", "", "b"]);
+  }
+
+  #[test]
+  fn leading_and_trailing() {
+    assert_eq!(collect("/finance"), vec!["", "finance"]);
+    assert_eq!(collect("finance/"), vec!["finance", ""]);
+  }
+
+  #[test]
+  fn separator_only() {
+    assert_eq!(collect("/"), vec!["", ""]);
+  }
+
+  #[test]
+  fn single_level() {
+    assert_eq!(collect("finance"), vec!["finance"]);
+    assert_eq!(collect("+"), vec!["+"]);
+  }
+}
@@ -1,30 +1,31 @@
-use mqtt_packet::{DataType, VariableByte};
+use mqtt_packet::{
+  encode_remaining_length, DataType, Error, FixedHeader, PacketType, Qos, VariableByte,
+};
 use mqtt_packet::{Identifier::*, Property};
-use std::collections::BTreeMap;
 use std::io;
 
 #[test]
 fn parse_byte() {
-  let data: Vec<u8> = vec![0x00, 0x04, 0x01, 0xFF, 0x24, 0x02];
+  let data: Vec<u8> = vec![0x04, 0x01, 0xFF, 0x24, 0x01];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&PayloadFormatIndicator) {
+  match property.get(PayloadFormatIndicator) {
     Some(value) => assert_eq!(value, &DataType::Byte(255)),
     None => panic!("Not a valid property"),
   }
 
-  match property.values.get(&MaximumQos) {
-    Some(value) => assert_eq!(value, &DataType::Byte(2)),
+  match property.get(MaximumQos) {
+    Some(value) => assert_eq!(value, &DataType::Byte(1)),
     None => panic!("Not a valid property"),
   }
 }
 
 #[test]
 fn parse_two_byte() {
-  let data: Vec<u8> = vec![0x00, 0x03, 0x13, 0x02, 0x03];
+  let data: Vec<u8> = vec![0x03, 0x13, 0x02, 0x03];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&ServerKeepAlive) {
+  match property.get(ServerKeepAlive) {
     Some(value) => assert_eq!(value, &DataType::TwoByteInteger(515)),
     None => panic!("Not a valid property"),
   }
@@ -32,10 +33,10 @@ fn parse_two_byte() {
 
 #[test]
 fn parse_four_byte() {
-  let data: Vec<u8> = vec![0x00, 0x05, 0x02, 0x02, 0x03, 0x04, 0x05];
+  let data: Vec<u8> = vec![0x05, 0x02, 0x02, 0x03, 0x04, 0x05];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&MessageExpiryInterval) {
+  match property.get(MessageExpiryInterval) {
     Some(value) => assert_eq!(value, &DataType::FourByteInteger(33_752_069)),
     None => panic!("Not a valid property"),
   }
@@ -43,10 +44,10 @@ fn parse_four_byte() {
 
 #[test]
 fn parse_variable_byte_one() {
-  let data: Vec<u8> = vec![0x00, 0x02, 0x0b, 0x7F];
+  let data: Vec<u8> = vec![0x02, 0x0b, 0x7F];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&SubscriptionIdentifier) {
+  match property.get(SubscriptionIdentifier) {
     Some(value) => assert_eq!(
       value,
       &DataType::VariableByteInteger(VariableByte::One(127))
@@ -57,10 +58,10 @@ fn parse_variable_byte_one() {
 
 #[test]
 fn parse_variable_byte_two() {
-  let data: Vec<u8> = vec![0x00, 0x03, 0x0b, 0xFF, 0x7F];
+  let data: Vec<u8> = vec![0x03, 0x0b, 0xFF, 0x7F];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&SubscriptionIdentifier) {
+  match property.get(SubscriptionIdentifier) {
     Some(value) => assert_eq!(
       value,
       &DataType::VariableByteInteger(VariableByte::Two(16383))
@@ -71,10 +72,10 @@ fn parse_variable_byte_two() {
 
 #[test]
 fn parse_variable_byte_three() {
-  let data: Vec<u8> = vec![0x00, 0x04, 0x0b, 0xFF, 0xFF, 0x7F];
+  let data: Vec<u8> = vec![0x04, 0x0b, 0xFF, 0xFF, 0x7F];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&SubscriptionIdentifier) {
+  match property.get(SubscriptionIdentifier) {
     Some(value) => assert_eq!(
       value,
       &DataType::VariableByteInteger(VariableByte::Three(2_097_151))
@@ -85,10 +86,10 @@ fn parse_variable_byte_three() {
 
 #[test]
 fn parse_variable_byte_four() {
-  let data: Vec<u8> = vec![0x00, 0x05, 0x0b, 0xFF, 0xFF, 0xFF, 0x7F];
+  let data: Vec<u8> = vec![0x05, 0x0b, 0xFF, 0xFF, 0xFF, 0x7F];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&SubscriptionIdentifier) {
+  match property.get(SubscriptionIdentifier) {
     Some(value) => assert_eq!(
       value,
       &DataType::VariableByteInteger(VariableByte::Four(268_435_455))
@@ -100,13 +101,13 @@ fn parse_variable_byte_four() {
 #[test]
 fn parse_binary_data() {
   let data: Vec<u8> = vec![
-    0, 13, 0x09, 0, 10, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A,
+    13, 0x09, 0, 10, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A,
   ];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
 
   let expected: Vec<u8> = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
-  match property.values.get(&CorrelationData) {
+  match property.get(CorrelationData) {
     Some(value) => assert_eq!(value, &DataType::BinaryData(expected)),
     None => panic!("Not a valid property"),
   }
@@ -115,11 +116,11 @@ fn parse_binary_data() {
 #[test]
 fn parse_utf8_string() {
   let data: Vec<u8> = vec![
-    0x00, 14, 0x1c, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 100, 100, 100,
+    14, 0x1c, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 100, 100, 100,
   ];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&ServerReference) {
+  match property.get(ServerReference) {
     Some(value) => assert_eq!(
       value,
       &DataType::Utf8EncodedString("hello world".to_string())
@@ -131,12 +132,12 @@ fn parse_utf8_string() {
 #[test]
 fn parse_utf8_string_pair() {
   let data: Vec<u8> = vec![
-    0, 23, 0x26, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 0, 7, 102, 111, 111,
-    32, 98, 97, 114, 1, 1, 1, 1,
+    23, 0x26, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 0, 7, 102, 111, 111, 32,
+    98, 97, 114, 1, 1, 1, 1,
   ];
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
-  match property.values.get(&UserProperty) {
+  match property.get(UserProperty) {
     Some(value) => assert_eq!(
       value,
       &DataType::Utf8StringPair("hello world".to_string(), "foo bar".to_string())
@@ -146,7 +147,7 @@ fn parse_utf8_string_pair() {
 }
 
 fn all_data() -> Vec<u8> {
-  let length: Vec<u8> = vec![0x00, 0x41];
+  let length: Vec<u8> = vec![0x41];
 
   let byte: Vec<u8> = vec![0x01, 0xFF];
   let two_byte: Vec<u8> = vec![0x13, 0x02, 0x03];
@@ -166,9 +167,9 @@ fn all_data() -> Vec<u8> {
     97, 114,
   ];
 
-  // these are sorted by the identifier keys used in
-  // parse_all and generate_all. PartialOrd sorts enum
-  // variants in the order they are declared.
+  // these are sorted by the numeric identifier values used
+  // in parse_all and generate_all, which is the order that
+  // Property::generate writes them in.
   [
     &length[..],
     &byte[..],
@@ -188,7 +189,8 @@ fn parse_all() {
   let mut reader = io::BufReader::new(&data[..]);
   let property = Property::new(&mut reader).unwrap();
 
-  for (identifier, value) in &property.values {
+  for (identifier, value) in &property {
+    assert_eq!(property.get_all(identifier).len(), 1);
     match identifier {
       PayloadFormatIndicator => assert_eq!(value, &DataType::Byte(255)),
       ServerKeepAlive => assert_eq!(value, &DataType::TwoByteInteger(515)),
@@ -218,93 +220,73 @@ fn parse_all() {
 
 #[test]
 fn generate_byte() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property
-    .values
-    .insert(PayloadFormatIndicator, DataType::Byte(255));
+  property.insert(PayloadFormatIndicator, DataType::Byte(255));
 
-  property.values.insert(MaximumQos, DataType::Byte(2));
+  property.insert(MaximumQos, DataType::Byte(1));
 
-  let expected: Vec<u8> = vec![0x00, 0x04, 0x01, 0xFF, 0x24, 0x02];
+  let expected: Vec<u8> = vec![0x04, 0x01, 0xFF, 0x24, 0x01];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_two_byte() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property
-    .values
-    .insert(ServerKeepAlive, DataType::TwoByteInteger(515));
+  property.insert(ServerKeepAlive, DataType::TwoByteInteger(515));
 
-  let expected: Vec<u8> = vec![0x00, 0x03, 0x13, 0x02, 0x03];
+  let expected: Vec<u8> = vec![0x03, 0x13, 0x02, 0x03];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_four_byte() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property
-    .values
-    .insert(MessageExpiryInterval, DataType::FourByteInteger(33_752_069));
+  property.insert(MessageExpiryInterval, DataType::FourByteInteger(33_752_069));
 
-  let expected: Vec<u8> = vec![0x00, 0x05, 0x02, 0x02, 0x03, 0x04, 0x05];
+  let expected: Vec<u8> = vec![0x05, 0x02, 0x02, 0x03, 0x04, 0x05];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_variable_byte() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property.values.insert(
+  property.insert(
     SubscriptionIdentifier,
     DataType::VariableByteInteger(VariableByte::Four(268_435_455)),
   );
 
-  let expected: Vec<u8> = vec![0x00, 0x05, 0x0b, 0xFF, 0xFF, 0xFF, 0x7F];
+  let expected: Vec<u8> = vec![0x05, 0x0b, 0xFF, 0xFF, 0xFF, 0x7F];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_binary_data() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
   let data: Vec<u8> = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
-  property
-    .values
-    .insert(CorrelationData, DataType::BinaryData(data));
+  property.insert(CorrelationData, DataType::BinaryData(data));
 
   let expected: Vec<u8> = vec![
-    0x00, 0x0D, 0x09, 0, 10, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
+    0x0D, 0x09, 0, 10, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
   ];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_utf8_string() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property.values.insert(
+  property.insert(
     ServerReference,
     DataType::Utf8EncodedString("hello world".to_string()),
   );
 
   let expected: Vec<u8> = vec![
-    0x00, 0x0E, 0x1c, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100,
+    0x0E, 0x1c, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100,
   ];
 
   assert_eq!(property.generate().unwrap(), expected);
@@ -312,57 +294,47 @@ fn generate_utf8_string() {
 
 #[test]
 fn generate_utf8_string_pair() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
-  property.values.insert(
+  let mut property = Property::default();
+  property.insert(
     UserProperty,
     DataType::Utf8StringPair("hello world".to_string(), "foo bar".to_string()),
   );
 
   let expected: Vec<u8> = vec![
-    0x00, 0x17, 0x26, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 0, 7, 102, 111,
-    111, 32, 98, 97, 114,
+    0x17, 0x26, 0, 11, 104, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100, 0, 7, 102, 111, 111,
+    32, 98, 97, 114,
   ];
   assert_eq!(property.generate().unwrap(), expected);
 }
 
 #[test]
 fn generate_all() {
-  let mut property = Property {
-    values: BTreeMap::new(),
-  };
+  let mut property = Property::default();
 
-  property
-    .values
-    .insert(PayloadFormatIndicator, DataType::Byte(255));
+  property.insert(PayloadFormatIndicator, DataType::Byte(255));
 
-  property
-    .values
-    .insert(ServerKeepAlive, DataType::TwoByteInteger(515));
+  property.insert(ServerKeepAlive, DataType::TwoByteInteger(515));
 
-  property
-    .values
-    .insert(MessageExpiryInterval, DataType::FourByteInteger(33_752_069));
+  property.insert(MessageExpiryInterval, DataType::FourByteInteger(33_752_069));
 
-  property.values.insert(
+  property.insert(
     SubscriptionIdentifier,
     DataType::VariableByteInteger(VariableByte::Four(268_435_455)),
   );
 
-  property.values.insert(
+  property.insert(
     CorrelationData,
     DataType::BinaryData(vec![
       0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
     ]),
   );
 
-  property.values.insert(
+  property.insert(
     ServerReference,
     DataType::Utf8EncodedString("hello world".to_string()),
   );
 
-  property.values.insert(
+  property.insert(
     UserProperty,
     DataType::Utf8StringPair("hello world".to_string(), "foo bar".to_string()),
   );
@@ -370,3 +342,836 @@ fn generate_all() {
   let expected = all_data();
   assert_eq!(property.generate().unwrap(), expected);
 }
+
+#[test]
+fn get_typed() {
+  let data = all_data();
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+
+  assert_eq!(property.get_u8(PayloadFormatIndicator), Some(255));
+  assert_eq!(property.get_u16(ServerKeepAlive), Some(515));
+  assert_eq!(property.get_u32(MessageExpiryInterval), Some(33_752_069));
+  assert_eq!(property.get_u32(SubscriptionIdentifier), Some(268_435_455));
+  assert_eq!(property.get_string(ServerReference), Some("hello world"));
+  assert_eq!(
+    property.get_binary(CorrelationData),
+    Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09][..])
+  );
+  assert_eq!(
+    property.get_user_properties(),
+    vec![("hello world", "foo bar")]
+  );
+
+  // absent properties and mismatched types both return None
+  assert_eq!(property.get_u8(MaximumQos), None);
+  assert_eq!(property.get_u16(PayloadFormatIndicator), None);
+  assert_eq!(property.get_string(CorrelationData), None);
+}
+
+#[test]
+fn generate_builder() {
+  let property = Property::builder()
+    .set_payload_format_indicator(true)
+    .set_server_keep_alive(515)
+    .set_message_expiry_interval(33_752_069)
+    .set_subscription_identifier(268_435_455)
+    .unwrap()
+    .set_correlation_data(vec![
+      0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
+    ])
+    .set_server_reference("hello world".to_string())
+    .add_user_property("hello world".to_string(), "foo bar".to_string())
+    .build();
+
+  // all_data uses 255 for the Payload Format Indicator, which the builder
+  // can't produce
+  let mut expected = all_data();
+  assert_eq!(expected[1..3], [0x01, 0xFF]);
+  expected[2] = 0x01;
+  assert_eq!(property.generate().unwrap(), expected);
+}
+
+#[test]
+fn parse_duplicate_error() {
+  let data: Vec<u8> = vec![0x04, 0x24, 0x01, 0x24, 0x00];
+  let mut reader = io::BufReader::new(&data[..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+}
+
+#[test]
+fn parse_repeated_user_property() {
+  let data: Vec<u8> = vec![
+    0x12, 0x0b, 0x01, 0x0b, 0x02, 0x26, 0, 1, 97, 0, 1, 49, 0x26, 0, 1, 98, 0, 1, 50,
+  ];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+
+  assert_eq!(property.get_user_properties(), vec![("a", "1"), ("b", "2")]);
+  assert_eq!(
+    property.get_all(SubscriptionIdentifier),
+    [
+      DataType::VariableByteInteger(VariableByte::One(1)),
+      DataType::VariableByteInteger(VariableByte::One(2)),
+    ]
+  );
+  assert_eq!(property.generate().unwrap(), data);
+}
+
+#[test]
+fn validate_packet_type() {
+  let data: Vec<u8> = vec![0x04, 0x0b, 0x01, 0x0b, 0x02];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+
+  assert_eq!(property.validate(PacketType::PUBLISH), Ok(()));
+  assert_eq!(
+    property.validate(PacketType::SUBSCRIBE),
+    Err(Error::MalformedPacket)
+  );
+  assert_eq!(
+    property.validate(PacketType::CONNECT),
+    Err(Error::MalformedPacket)
+  );
+}
+
+#[test]
+fn validate_subscribe_identifier() {
+  let one = Property::builder()
+    .set_subscription_identifier(1)
+    .unwrap()
+    .build();
+  assert_eq!(one.validate(PacketType::SUBSCRIBE), Ok(()));
+
+  let max = Property::builder()
+    .set_subscription_identifier(268_435_455)
+    .unwrap()
+    .build();
+  assert_eq!(max.validate(PacketType::SUBSCRIBE), Ok(()));
+
+  // the builder rejects 0, so insert it directly
+  let mut zero = Property::builder().build();
+  zero.insert(
+    SubscriptionIdentifier,
+    DataType::VariableByteInteger(VariableByte::One(0)),
+  );
+  assert_eq!(
+    zero.validate(PacketType::SUBSCRIBE),
+    Err(Error::ProtocolError)
+  );
+
+  let data: Vec<u8> = vec![0x04, 0x0b, 0x01, 0x0b, 0x02];
+  let mut reader = io::BufReader::new(&data[..]);
+  let two = Property::new(&mut reader).unwrap();
+  assert_eq!(
+    two.validate(PacketType::SUBSCRIBE),
+    Err(Error::MalformedPacket)
+  );
+}
+
+#[test]
+fn generate_user_property_order() {
+  let property = Property::builder()
+    .add_user_property("b".to_string(), "1".to_string())
+    .add_user_property("a".to_string(), "2".to_string())
+    .set_payload_format_indicator(true)
+    .build();
+
+  let expected: Vec<u8> = vec![
+    0x10, 0x01, 0x01, 0x26, 0, 1, 98, 0, 1, 49, 0x26, 0, 1, 97, 0, 1, 50,
+  ];
+  assert_eq!(property.generate().unwrap(), expected);
+}
+
+#[test]
+fn iter_wire_order() {
+  let property = Property::builder()
+    .add_user_property("b".to_string(), "1".to_string())
+    .set_topic_alias(1)
+    .add_user_property("a".to_string(), "2".to_string())
+    .set_payload_format_indicator(true)
+    .build();
+
+  let order: Vec<_> = property.iter_wire_order().collect();
+  assert_eq!(
+    order,
+    vec![
+      (PayloadFormatIndicator, &DataType::Byte(1)),
+      (TopicAlias, &DataType::TwoByteInteger(1)),
+      (
+        UserProperty,
+        &DataType::Utf8StringPair("b".to_string(), "1".to_string())
+      ),
+      (
+        UserProperty,
+        &DataType::Utf8StringPair("a".to_string(), "2".to_string())
+      ),
+    ]
+  );
+}
+
+#[test]
+fn parse_empty() {
+  let data: Vec<u8> = vec![0x00, 0x01];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  assert!(property.is_empty());
+}
+
+#[test]
+fn generate_empty() {
+  let property = Property::builder().build();
+  assert_eq!(property.generate().unwrap(), vec![0x00]);
+
+  let mut reader = io::BufReader::new(&[0x00][..]);
+  assert_eq!(Property::new(&mut reader).unwrap(), property);
+}
+
+#[test]
+fn validate_topic_alias() {
+  let one = Property::builder().set_topic_alias(1).build();
+  assert_eq!(one.validate(PacketType::PUBLISH), Ok(()));
+
+  let zero = Property::builder().set_topic_alias(0).build();
+  assert_eq!(
+    zero.validate(PacketType::PUBLISH),
+    Err(Error::ProtocolError)
+  );
+}
+
+#[test]
+fn validate_values() {
+  let property = Property::builder()
+    .set_topic_alias(1)
+    .set_receive_maximum(1)
+    .set_maximum_packet_size(1)
+    .set_server_keep_alive(0)
+    .build();
+  assert_eq!(property.validate_values(), Ok(()));
+
+  // not valid in a PUBLISH, but every value is in range
+  assert_eq!(
+    property.validate(PacketType::PUBLISH),
+    Err(Error::MalformedPacket)
+  );
+
+  let mut property = Property::builder().build();
+  property.insert(ReceiveMaximum, DataType::TwoByteInteger(0));
+  assert_eq!(property.validate_values(), Err(Error::ProtocolError));
+
+  let mut property = Property::builder().build();
+  property.insert(MaximumPacketSize, DataType::FourByteInteger(0));
+  assert_eq!(property.validate_values(), Err(Error::ProtocolError));
+
+  let mut property = Property::builder().build();
+  property.insert(RetainAvailable, DataType::Byte(2));
+  assert_eq!(property.validate_values(), Err(Error::ProtocolError));
+
+  let mut property = Property::builder().build();
+  property.insert(SubscriptionIdentifier, DataType::variable_byte(0).unwrap());
+  assert_eq!(property.validate_values(), Err(Error::ProtocolError));
+
+  let mut property = Property::builder().build();
+  property.insert(TopicAlias, DataType::Byte(1));
+  assert_eq!(property.validate_values(), Err(Error::MalformedPacket));
+}
+
+#[test]
+fn property_length_over_127_bytes() {
+  let value = "x".repeat(193);
+  let property = Property::builder().set_reason_string(value.clone()).build();
+
+  // 1 identifier byte + 2 length bytes + 193 string bytes = 196 (0xC4)
+  let bytes = property.generate().unwrap();
+  assert_eq!(&bytes[..3], &[0xC4, 0x01, 0x1f]);
+  assert_eq!(bytes.len(), 198);
+
+  let mut reader = io::BufReader::new(&bytes[..]);
+  let parsed = Property::new(&mut reader).unwrap();
+  assert_eq!(parsed.get_string(ReasonString), Some(value.as_str()));
+}
+
+#[test]
+fn parse_counted() {
+  let mut data = all_data();
+  let length = data.len();
+  data.extend_from_slice(&[0xAA, 0xBB, 0xCC]);
+
+  let mut reader = io::BufReader::new(&data[..]);
+  let (property, consumed) = Property::parse_counted(&mut reader).unwrap();
+  assert_eq!(consumed, length);
+  assert_eq!(property.len(), 7);
+
+  let mut reader = io::BufReader::new(&[0x00][..]);
+  let (_, consumed) = Property::parse_counted(&mut reader).unwrap();
+  assert_eq!(consumed, 1);
+}
+
+#[test]
+fn validate_auth() {
+  let paired = Property::builder()
+    .set_authentication_method("SCRAM-SHA-256".to_string())
+    .set_authentication_data(vec![0x01, 0x02])
+    .build();
+  assert_eq!(paired.validate_auth(), Ok(()));
+
+  let method = Property::builder()
+    .set_authentication_method("SCRAM-SHA-256".to_string())
+    .build();
+  assert_eq!(method.validate_auth(), Ok(()));
+
+  let data = Property::builder()
+    .set_authentication_data(vec![0x01, 0x02])
+    .build();
+  assert_eq!(data.validate_auth(), Err(Error::ProtocolError));
+}
+
+#[test]
+fn validate_continue_auth() {
+  let scram = Property::builder()
+    .set_authentication_method("SCRAM-SHA-256".to_string())
+    .set_authentication_data(b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL".to_vec())
+    .set_reason_string("server-first-message".to_string())
+    .build();
+  assert_eq!(scram.validate_continue_auth(), Ok(()));
+
+  let method = Property::builder()
+    .set_authentication_method("SCRAM-SHA-256".to_string())
+    .build();
+  assert_eq!(method.validate_continue_auth(), Err(Error::ProtocolError));
+
+  let data = Property::builder()
+    .set_authentication_data(vec![0x01])
+    .build();
+  assert_eq!(data.validate_continue_auth(), Err(Error::ProtocolError));
+
+  let not_for_auth = Property::builder()
+    .set_authentication_method("SCRAM-SHA-256".to_string())
+    .set_authentication_data(vec![0x01])
+    .set_session_expiry_interval(10)
+    .build();
+  assert_eq!(
+    not_for_auth.validate_continue_auth(),
+    Err(Error::MalformedPacket)
+  );
+}
+
+#[test]
+fn into_iterator() {
+  let property = Property::builder()
+    .set_topic_alias(3)
+    .set_payload_format_indicator(true)
+    .add_user_property("a".into(), "1".into())
+    .add_user_property("b".into(), "2".into())
+    .build();
+
+  let expected = vec![
+    (PayloadFormatIndicator, DataType::Byte(1)),
+    (TopicAlias, DataType::TwoByteInteger(3)),
+    (
+      UserProperty,
+      DataType::Utf8StringPair("a".into(), "1".into()),
+    ),
+    (
+      UserProperty,
+      DataType::Utf8StringPair("b".into(), "2".into()),
+    ),
+  ];
+
+  let mut borrowed = vec![];
+  for (id, value) in &property {
+    borrowed.push((id, value));
+  }
+  let expected_refs: Vec<_> = expected.iter().map(|(id, value)| (*id, value)).collect();
+  assert_eq!(borrowed, expected_refs);
+
+  let bytes = property.generate().unwrap();
+  let owned: Vec<_> = property.into_iter().collect();
+  assert_eq!(owned, expected);
+
+  let collected: Property = owned.into_iter().collect();
+  assert_eq!(collected.generate().unwrap(), bytes);
+}
+
+#[test]
+fn merge() {
+  let mut property = Property::builder()
+    .set_subscription_identifier(1)
+    .unwrap()
+    .set_topic_alias(1)
+    .add_user_property("a".into(), "1".into())
+    .build();
+
+  let other = Property::builder()
+    .set_subscription_identifier(2)
+    .unwrap()
+    .set_topic_alias(2)
+    .add_user_property("b".into(), "2".into())
+    .build();
+
+  property.merge(other);
+
+  assert_eq!(
+    property.get_all(SubscriptionIdentifier),
+    [
+      DataType::VariableByteInteger(VariableByte::One(1)),
+      DataType::VariableByteInteger(VariableByte::One(2)),
+    ]
+  );
+  assert_eq!(property.get_u16(TopicAlias), Some(2));
+  assert_eq!(property.get_user_properties(), vec![("a", "1"), ("b", "2")]);
+}
+
+#[test]
+fn from_slice() {
+  let data = all_data();
+  let mut reader = io::BufReader::new(&data[..]);
+  let expected = Property::new(&mut reader).unwrap();
+
+  let (property, consumed) = Property::from_slice(&data).unwrap();
+  assert_eq!(property, expected);
+  assert_eq!(consumed, data.len());
+
+  let err = Property::from_slice(&data[..data.len() - 1]).unwrap_err();
+  assert_eq!(err, Error::Incomplete { needed: Some(1) });
+
+  let err = Property::from_slice(&[]).unwrap_err();
+  assert_eq!(err, Error::Incomplete { needed: None });
+
+  // the whole declared block is present, so a value cut short inside it is
+  // an error in the packet rather than a reason to wait for more bytes. a
+  // reader can't tell, so it reports the same bytes as incomplete.
+  let data: Vec<u8> = vec![0x02, 0x23, 0x00];
+  let err = Property::from_slice(&data).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+
+  let mut reader = io::BufReader::new(&data[..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::Incomplete { needed: None });
+}
+
+#[test]
+fn parse_lenient() {
+  // Topic Alias, an unknown identifier 0x7F, then Maximum QoS
+  let data: Vec<u8> = vec![0x08, 0x23, 0x00, 0x01, 0x7F, 0xAA, 0xBB, 0x24, 0x01, 0xFF];
+  let mut reader = io::BufReader::new(&data[..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new_lenient(&mut reader).unwrap();
+  assert_eq!(property.get_u16(TopicAlias), Some(1));
+  assert_eq!(property.get(MaximumQos), None);
+
+  // the rest of the block is skipped, but nothing past it
+  let mut rest = vec![];
+  io::Read::read_to_end(&mut reader, &mut rest).unwrap();
+  assert_eq!(rest, vec![0xFF]);
+
+  // the reader ends before the declared length of the block
+  let mut reader = io::BufReader::new(&data[..8]);
+  let err = Property::new_lenient(&mut reader).unwrap_err();
+  assert_eq!(err, Error::Incomplete { needed: Some(1) });
+}
+
+#[test]
+fn validate_data_type() {
+  let mut property = Property::builder().build();
+  property.insert(TopicAlias, DataType::FourByteInteger(1));
+
+  let err = property.validate(PacketType::PUBLISH).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+
+  property.insert(TopicAlias, DataType::TwoByteInteger(1));
+  assert!(property.validate(PacketType::PUBLISH).is_ok());
+}
+
+#[test]
+fn validate_receive_maximum() {
+  let data: Vec<u8> = vec![0x03, 0x21, 0x00, 0x00];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  let err = property.validate_connect_constraints().unwrap_err();
+  assert_eq!(err, Error::ProtocolError);
+
+  let property = Property::builder().set_receive_maximum(1).build();
+  assert!(property.validate_connect_constraints().is_ok());
+}
+
+#[test]
+fn validate_connect_constraints_data_type() {
+  let mut property = Property::builder().build();
+  property.insert(ReceiveMaximum, DataType::Byte(0));
+  let err = property.validate_connect_constraints().unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+
+  let mut property = Property::builder().build();
+  property.insert(MaximumPacketSize, DataType::TwoByteInteger(0));
+  let err = property.validate_connect_constraints().unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+}
+
+#[test]
+fn validate_maximum_packet_size() {
+  let data: Vec<u8> = vec![0x05, 0x27, 0x00, 0x00, 0x00, 0x00];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  let err = property.validate_connect_constraints().unwrap_err();
+  assert_eq!(err, Error::ProtocolError);
+
+  let property = Property::builder()
+    .set_receive_maximum(1)
+    .set_maximum_packet_size(1)
+    .build();
+  assert!(property.validate_connect_constraints().is_ok());
+}
+
+#[test]
+fn parse_subscription_identifier_zero() {
+  let data: Vec<u8> = vec![0x02, 0x0b, 0x00];
+  let mut reader = io::BufReader::new(&data[..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::ProtocolError);
+
+  // a non-minimal encoding of 0 is still 0
+  let data: Vec<u8> = vec![0x03, 0x0b, 0x80, 0x00];
+  let mut reader = io::BufReader::new(&data[..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::ProtocolError);
+
+  let data: Vec<u8> = vec![0x05, 0x0b, 0xFF, 0xFF, 0xFF, 0x7F];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  assert_eq!(property.get_u32(SubscriptionIdentifier), Some(268_435_455));
+}
+
+#[test]
+fn encoded_len() {
+  let data = all_data();
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  assert_eq!(property.encoded_len().unwrap() as usize, data.len());
+
+  let empty = Property::builder().build();
+  assert_eq!(empty.encoded_len().unwrap(), 1);
+
+  let long = Property::builder()
+    .set_reason_string("a".repeat(200))
+    .add_user_property("name".into(), "value".into())
+    .build();
+  let len = long.generate().unwrap().len();
+  assert_eq!(long.encoded_len().unwrap() as usize, len);
+}
+
+#[test]
+fn validate_will() {
+  let will = Property::builder()
+    .set_will_delay_interval(60)
+    .set_payload_format_indicator(true)
+    .set_message_expiry_interval(3600)
+    .set_content_type("text/plain".into())
+    .set_response_topic("response".into())
+    .set_correlation_data(vec![0x01])
+    .add_user_property("a".into(), "1".into())
+    .build();
+  assert_eq!(will.validate_will(), Ok(()));
+
+  // Will Delay Interval is only valid in a will block
+  assert_eq!(
+    will.validate(PacketType::CONNECT),
+    Err(Error::MalformedPacket)
+  );
+
+  let session = Property::builder()
+    .set_will_delay_interval(60)
+    .set_session_expiry_interval(60)
+    .build();
+  assert_eq!(session.validate_will(), Err(Error::MalformedPacket));
+}
+
+#[test]
+fn validate_boolean_bytes() {
+  let data: Vec<u8> = vec![0x02, 0x01, 0x02];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  assert_eq!(
+    property.validate(PacketType::PUBLISH),
+    Err(Error::ProtocolError)
+  );
+  assert_eq!(property.validate_will(), Err(Error::ProtocolError));
+
+  let mut property = Property::builder()
+    .set_request_problem_information(true)
+    .build();
+  assert_eq!(property.validate(PacketType::CONNECT), Ok(()));
+
+  property.insert(RequestResponseInformation, DataType::Byte(2));
+  assert_eq!(
+    property.validate(PacketType::CONNECT),
+    Err(Error::ProtocolError)
+  );
+}
+
+#[test]
+fn validate_maximum_qos() {
+  let data: Vec<u8> = vec![0x02, 0x24, 0x02];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+  assert_eq!(
+    property.validate(PacketType::CONNACK),
+    Err(Error::ProtocolError)
+  );
+
+  let property = Property::builder()
+    .set_maximum_qos(Qos::AtLeastOnce)
+    .build();
+  assert_eq!(property.validate(PacketType::CONNACK), Ok(()));
+}
+
+#[test]
+fn user_properties() {
+  let data: Vec<u8> = vec![
+    0x15, 0x26, 0x00, 0x01, 0x63, 0x00, 0x01, 0x33, 0x26, 0x00, 0x01, 0x61, 0x00, 0x01, 0x31, 0x26,
+    0x00, 0x01, 0x62, 0x00, 0x01, 0x32,
+  ];
+  let mut reader = io::BufReader::new(&data[..]);
+  let property = Property::new(&mut reader).unwrap();
+
+  assert_eq!(
+    property.user_properties(),
+    vec![
+      (String::from("c"), String::from("3")),
+      (String::from("a"), String::from("1")),
+      (String::from("b"), String::from("2")),
+    ]
+  );
+}
+
+#[test]
+fn parse_reserved_identifier() {
+  for id in [0x00, 0x04, 0x07, 0x0a, 0x0c, 0x10, 0x2b, 0x7f].iter() {
+    let data: Vec<u8> = vec![0x02, *id, 0x00];
+    let mut reader = io::BufReader::new(&data[..]);
+    let err = Property::new(&mut reader).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+  }
+}
+
+#[test]
+fn from_block() {
+  let data = all_data();
+  let mut reader = io::BufReader::new(&data[..]);
+  let expected = Property::new(&mut reader).unwrap();
+
+  // all_data has a one byte length prefix
+  let block = &data[1..];
+  assert_eq!(Property::from_block(block).unwrap(), expected);
+
+  let err = Property::from_block(&block[..block.len() - 1]).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+
+  // a Two Byte Integer, a Variable Byte Integer and a Four Byte Integer cut
+  // short inside the block
+  for truncated in [
+    &[0x23, 0x00][..],
+    &[0x0B, 0x80][..],
+    &[0x02, 0x00, 0x00][..],
+  ] {
+    let err = Property::from_block(truncated).unwrap_err();
+    assert_eq!(err, Error::MalformedPacket);
+  }
+
+  assert_eq!(
+    Property::from_block(&[]).unwrap(),
+    Property::builder().build()
+  );
+}
+
+#[test]
+fn validate_topic_alias_maximum() {
+  let property = Property::builder().set_topic_alias(10).build();
+  assert_eq!(property.validate_topic_alias(10), Ok(()));
+  assert_eq!(property.validate_topic_alias(9), Err(Error::ProtocolError));
+  assert_eq!(property.validate_topic_alias(0), Err(Error::ProtocolError));
+
+  let zero = Property::builder().set_topic_alias(0).build();
+  assert_eq!(zero.validate_topic_alias(10), Err(Error::ProtocolError));
+
+  let none = Property::builder().build();
+  assert_eq!(none.validate_topic_alias(0), Ok(()));
+}
+
+#[test]
+fn to_debug_string() {
+  let property = Property::builder()
+    .add_user_property(String::from("region"), String::from("eu"))
+    .set_correlation_data(vec![0x01, 0x02])
+    .set_content_type(String::from("text/plain"))
+    .add_user_property(String::from("region"), String::from("us"))
+    .build();
+
+  let expected = [
+    "ContentType = \"text/plain\"",
+    "CorrelationData = 0x0102",
+    "UserProperty = \"region\": \"eu\"",
+    "UserProperty = \"region\": \"us\"",
+  ];
+  assert_eq!(property.to_debug_string(), expected.join("\n"));
+
+  assert_eq!(Property::builder().build().to_debug_string(), "");
+}
+
+#[test]
+fn large_values_round_trip() {
+  let property = Property::builder()
+    .set_reason_string("a".repeat(65535))
+    .set_topic_alias(1)
+    .add_user_property("n".repeat(40000), "v".repeat(40000))
+    .build();
+
+  let bytes = property.generate().unwrap();
+
+  let mut reader = io::BufReader::new(&bytes[..]);
+  assert_eq!(Property::new(&mut reader).unwrap(), property);
+
+  let (parsed, consumed) = Property::from_slice(&bytes).unwrap();
+  assert_eq!(parsed, property);
+  assert_eq!(consumed, bytes.len());
+
+  let mut reader = io::BufReader::new(&bytes[..]);
+  let (parsed, consumed) = Property::parse_counted(&mut reader).unwrap();
+  assert_eq!(parsed, property);
+  assert_eq!(consumed, bytes.len());
+
+  // the block is over 16,383 bytes, so the Property Length takes 3 bytes
+  assert_eq!(Property::from_block(&bytes[3..]).unwrap(), property);
+}
+
+#[test]
+fn set_subscription_identifier_range() {
+  assert_eq!(
+    Property::builder()
+      .set_subscription_identifier(0)
+      .unwrap_err(),
+    Error::GenerateError
+  );
+  assert_eq!(
+    Property::builder()
+      .set_subscription_identifier(268_435_456)
+      .unwrap_err(),
+    Error::GenerateError
+  );
+
+  let property = Property::builder()
+    .set_subscription_identifier(200)
+    .unwrap()
+    .build();
+  assert_eq!(
+    property.get(SubscriptionIdentifier),
+    Some(&DataType::VariableByteInteger(VariableByte::Two(200)))
+  );
+}
+
+#[test]
+fn builder_maximum_qos() {
+  for (qos, expected) in [
+    (Qos::AtMostOnce, Some(0)),
+    (Qos::AtLeastOnce, Some(1)),
+    (Qos::ExactlyOnce, None),
+  ] {
+    let property = Property::builder().set_maximum_qos(qos).build();
+    assert_eq!(property.get_u8(MaximumQos), expected);
+    assert_eq!(property.validate(PacketType::CONNACK), Ok(()));
+  }
+
+  // a later ExactlyOnce clears an earlier value
+  let property = Property::builder()
+    .set_maximum_qos(Qos::AtMostOnce)
+    .set_maximum_qos(Qos::ExactlyOnce)
+    .build();
+  assert_eq!(property.get_u8(MaximumQos), None);
+
+  let property = Property::builder()
+    .set_payload_format_indicator(false)
+    .build();
+  assert_eq!(property.get_u8(PayloadFormatIndicator), Some(0));
+}
+
+#[test]
+fn accessors() {
+  let mut property = Property::builder().build();
+  assert!(property.is_empty());
+  assert_eq!(property.len(), 0);
+
+  property.insert(TopicAlias, DataType::TwoByteInteger(1));
+  property.push(
+    UserProperty,
+    DataType::Utf8StringPair("a".into(), "1".into()),
+  );
+  property.push(
+    UserProperty,
+    DataType::Utf8StringPair("b".into(), "2".into()),
+  );
+  assert_eq!(property.len(), 3);
+  assert_eq!(property.get_all(UserProperty).len(), 2);
+
+  // insert replaces every existing value
+  property.insert(
+    UserProperty,
+    DataType::Utf8StringPair("c".into(), "3".into()),
+  );
+  assert_eq!(property.get_user_properties(), vec![("c", "3")]);
+
+  assert_eq!(
+    property.remove(TopicAlias),
+    vec![DataType::TwoByteInteger(1)]
+  );
+  assert_eq!(property.remove(TopicAlias), vec![]);
+  assert_eq!(property.get(TopicAlias), None);
+  assert_eq!(property.len(), 1);
+}
+
+#[test]
+fn parse_chunked() {
+  let mut packet = vec![0x30];
+  packet.extend(encode_remaining_length(200).unwrap());
+  let properties = Property::builder()
+    .set_topic_alias(1)
+    .set_content_type(String::from("text/plain"))
+    .add_user_property(String::from("a"), String::from("1"))
+    .build()
+    .generate()
+    .unwrap();
+
+  // every prefix of the fixed header is incomplete, not an error
+  for end in 0..3 {
+    let mut reader = io::BufReader::new(&packet[..end]);
+    let err = FixedHeader::new(&mut reader).unwrap_err();
+    assert_eq!(err, Error::Incomplete { needed: None });
+  }
+  let mut reader = io::BufReader::new(&packet[..]);
+  assert_eq!(
+    FixedHeader::new(&mut reader).unwrap().remaining_length(),
+    200
+  );
+
+  // every prefix of the property block is incomplete
+  for end in 0..properties.len() {
+    let mut reader = io::BufReader::new(&properties[..end]);
+    let err = Property::new(&mut reader).unwrap_err();
+    assert!(
+      matches!(err, Error::Incomplete { .. }),
+      "{}: {:?}",
+      end,
+      err
+    );
+  }
+  let mut reader = io::BufReader::new(&properties[..]);
+  assert!(Property::new(&mut reader).is_ok());
+
+  // invalid bytes are still an error, even when the input is short
+  let mut reader = io::BufReader::new(&[0x02, 0x7F][..]);
+  let err = Property::new(&mut reader).unwrap_err();
+  assert_eq!(err, Error::MalformedPacket);
+}
@@ -0,0 +1,111 @@
+use mqtt_packet::{DataType, Identifier, Identifier::*, Property, VariableByte};
+use proptest::prelude::*;
+use std::io;
+
+// Every byte property is a boolean or Maximum QoS, which may only be 0 or 1.
+fn byte() -> impl Strategy<Value = DataType> {
+  (0..=1u8).prop_map(DataType::Byte)
+}
+
+fn two_byte_int(min: u16) -> impl Strategy<Value = DataType> {
+  (min..=u16::MAX).prop_map(DataType::TwoByteInteger)
+}
+
+fn four_byte_int(min: u32) -> impl Strategy<Value = DataType> {
+  (min..=u32::MAX).prop_map(DataType::FourByteInteger)
+}
+
+fn variable_byte_int() -> impl Strategy<Value = DataType> {
+  (1..=268_435_455u32).prop_map(|value| DataType::VariableByteInteger(VariableByte::from(value)))
+}
+
+// Strings and binary data are mostly short, but sometimes large enough to
+// need a multi-byte Property Length, up to the 65,535 byte limit.
+fn string() -> impl Strategy<Value = String> {
+  prop_oneof![
+    4 => "\\PC{0,32}",
+    // at most 4 bytes per char, so this stays within 65,535 bytes
+    1 => "\\PC{0,16383}",
+    1 => prop::collection::vec(b'a'..=b'z', 65500..=65535)
+      .prop_map(|bytes| String::from_utf8(bytes).unwrap()),
+  ]
+}
+
+fn utf8_string() -> impl Strategy<Value = DataType> {
+  string().prop_map(DataType::Utf8EncodedString)
+}
+
+fn binary_data() -> impl Strategy<Value = DataType> {
+  prop_oneof![
+    4 => prop::collection::vec(any::<u8>(), 0..64),
+    1 => prop::collection::vec(any::<u8>(), 0..=65535),
+  ]
+  .prop_map(DataType::BinaryData)
+}
+
+fn utf8_string_pair() -> impl Strategy<Value = DataType> {
+  (string(), string()).prop_map(|(name, value)| DataType::Utf8StringPair(name, value))
+}
+
+fn entry() -> impl Strategy<Value = (Identifier, DataType)> {
+  let bytes = prop::sample::select(vec![
+    PayloadFormatIndicator,
+    RequestProblemInformation,
+    RequestResponseInformation,
+    MaximumQos,
+    RetainAvailable,
+    WildcardSubscriptionAvailable,
+    SubscriptionIdentifierAvailable,
+    SharedSubscriptionAvailable,
+  ]);
+
+  let two_byte_ints = prop::sample::select(vec![ServerKeepAlive, TopicAliasMaximum]);
+  let non_zero_two_byte_ints = prop::sample::select(vec![ReceiveMaximum, TopicAlias]);
+
+  let four_byte_ints = prop::sample::select(vec![
+    MessageExpiryInterval,
+    SessionExpiryInterval,
+    WillDelayInterval,
+  ]);
+
+  let strings = prop::sample::select(vec![
+    ContentType,
+    ResponseTopic,
+    AssignedClientIdentifier,
+    AuthenticationMethod,
+    ResponseInformation,
+    ServerReference,
+    ReasonString,
+  ]);
+
+  let binary = prop::sample::select(vec![CorrelationData, AuthenticationData]);
+
+  prop_oneof![
+    (bytes, byte()),
+    (two_byte_ints, two_byte_int(0)),
+    (non_zero_two_byte_ints, two_byte_int(1)),
+    (four_byte_ints, four_byte_int(0)),
+    (Just(MaximumPacketSize), four_byte_int(1)),
+    (Just(SubscriptionIdentifier), variable_byte_int()),
+    (strings, utf8_string()),
+    (binary, binary_data()),
+    (Just(UserProperty), utf8_string_pair()),
+  ]
+}
+
+fn property() -> impl Strategy<Value = Property> {
+  prop::collection::vec(entry(), 0..16).prop_map(|entries| entries.into_iter().collect())
+}
+
+proptest! {
+  #[test]
+  fn generate_parse_round_trip(property in property()) {
+    prop_assert!(property.validate_values().is_ok());
+
+    let bytes = property.generate().unwrap();
+    let mut reader = io::BufReader::new(&bytes[..]);
+    let parsed = Property::new(&mut reader).unwrap();
+    prop_assert!(parsed.validate_values().is_ok());
+    prop_assert_eq!(parsed, property);
+  }
+}